@@ -0,0 +1,51 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use hyprlux::shaders::window_context::WindowContext;
+use hyprlux::shaders::window_rule::{self, WindowRule};
+use hyprlux::shaders::workspace::WorkspaceInfo;
+use hyprlux::shaders::{matcher, rule_set};
+
+const RULES: usize = 300;
+
+fn rules() -> Vec<WindowRule> {
+    (0..RULES)
+        .map(|i| match i % 3 {
+            0 => window_rule::new(matcher::exact(&format!("steam_app_{}", i)).ok(), None),
+            1 => window_rule::new(None, matcher::glob(&format!("Game {}*", i)).ok()),
+            _ => window_rule::new(
+                matcher::regex(&format!("^(app{})$", i)).ok(),
+                matcher::regex(&format!("^(window{})$", i)).ok(),
+            ),
+        })
+        .collect()
+}
+
+fn focus_change(c: &mut Criterion) {
+    let rules = rules();
+    let rule_set = rule_set::new(rules.clone()).unwrap();
+    // Matches only the last rule, so every rule has to be evaluated
+    let context = WindowContext {
+        class: Some(format!("app{}", RULES - 1)),
+        title: Some(format!("window{}", RULES - 1)),
+        workspace: Some(WorkspaceInfo {
+            id: 1,
+            name: "1".to_string(),
+        }),
+        ..WindowContext::default()
+    };
+
+    let mut group = c.benchmark_group("focus_change");
+    group.bench_function("rule_set", |b| {
+        b.iter(|| rule_set.matches(black_box(&context)))
+    });
+    group.bench_function("each_rule", |b| {
+        b.iter(|| {
+            rules
+                .iter()
+                .position(|rule| rule.matches(black_box(&context)))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, focus_change);
+criterion_main!(benches);
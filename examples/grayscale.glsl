@@ -0,0 +1,5 @@
+// Placeholders like {strength} are filled from `vars`, so literal braces are doubled
+vec3 {name}(vec3 color) {{
+    float luma = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    return mix(color, vec3(luma), {strength});
+}}
@@ -50,19 +50,93 @@ let
         type = lib.types.int;
         default = 3500;
       };
+      transition_duration = lib.mkOption {
+        description = "Minutes over which the temperature fades in and out";
+        type = lib.types.nullOr lib.types.ints.unsigned;
+        default = null;
+      };
+      transition_step = lib.mkOption {
+        description = "Temperature step in kelvin used while transitioning";
+        type = lib.types.nullOr lib.types.ints.positive;
+        default = null;
+      };
+      exclude = lib.mkOption {
+        description = "Windows that suppress night light while focused";
+        type = lib.types.listOf windowRuleSubmodule;
+        default = [ ];
+      };
+      monitor = lib.mkOption {
+        description = "Monitor name or description regex to limit night light to";
+        type = lib.types.nullOr lib.types.str;
+        default = null;
+      };
+    };
+  };
+
+  matcherType = lib.types.either lib.types.str (lib.types.attrsOf lib.types.anything);
+
+  windowConditionOptions =
+    lib.genAttrs [ "fullscreen" "floating" "xwayland" "pinned" ] (
+      condition:
+      lib.mkOption {
+        description = "Only match windows that are ${condition} (true) or not (false)";
+        type = lib.types.nullOr lib.types.bool;
+        default = null;
+      }
+    )
+    // {
+      match = lib.mkOption {
+        description = "Whether all (default) or any of window_class and window_title must match";
+        type = lib.types.nullOr (lib.types.enum [ "all" "any" ]);
+        default = null;
+      };
+      initial_class = lib.mkOption {
+        description = "Matcher for the class the window was created with";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      initial_title = lib.mkOption {
+        description = "Matcher for the title the window was created with";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      pid = lib.mkOption {
+        description = "Process ID of the window";
+        type = lib.types.nullOr lib.types.int;
+        default = null;
+      };
     };
+
+  windowRuleSubmodule = lib.types.submodule {
+    options = {
+      window_class = lib.mkOption {
+        description = "Window class regex, or { exact | glob | regex | not = ...; }";
+        type = matcherType;
+        default = "";
+      };
+      window_title = lib.mkOption {
+        description = "Window title regex, or { exact | glob | regex | not = ...; }";
+        type = matcherType;
+        default = "";
+      };
+      workspace = lib.mkOption {
+        description = "Workspace ID or name matcher";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+    } // windowConditionOptions;
   };
 
   vibranceSubmodule = lib.types.submodule {
     options = {
       window_class = lib.mkOption {
-        description = "Window class name or regex";
-        type = lib.types.str;
+        description = "Window class regex, or { exact | glob | regex | not = ...; }";
+        type = matcherType;
         default = "";
       };
       window_title = lib.mkOption {
-        description = "Window title name or regex";
-        type = lib.types.str;
+        description = "Window title regex, or { exact | glob | regex | not = ...; }";
+        type = matcherType;
         default = "";
       };
       strength = lib.mkOption {
@@ -70,9 +144,139 @@ let
         type = lib.types.int;
         default = 100;
       };
+      workspace = lib.mkOption {
+        description = "Workspace ID or name matcher, all workspaces if unset";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      monitor = lib.mkOption {
+        description = "Monitor name or description regex, all monitors if unset";
+        type = lib.types.nullOr lib.types.str;
+        default = null;
+      };
+    } // windowConditionOptions;
+  };
+
+  shaderSourceOptions = {
+    name = lib.mkOption {
+      description = "Name of the shader, its source defines `vec3 {name}(vec3 color)`";
+      type = lib.types.str;
+    };
+    path = lib.mkOption {
+      description = "Path to a GLSL file, takes precedence over source";
+      type = lib.types.nullOr lib.types.str;
+      default = null;
+    };
+    source = lib.mkOption {
+      description = "Inline GLSL source";
+      type = lib.types.nullOr lib.types.lines;
+      default = null;
     };
+    vars = lib.mkOption {
+      description = "Values for the {placeholders} in the source";
+      type = lib.types.attrsOf lib.types.str;
+      default = { };
+    };
+  };
+
+  customShaderSubmodule = lib.types.submodule {
+    options = shaderSourceOptions // {
+      window_class = lib.mkOption {
+        description = "Window class regex, or { exact | glob | regex | not = ...; }";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      window_title = lib.mkOption {
+        description = "Window title regex, or { exact | glob | regex | not = ...; }";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      workspace = lib.mkOption {
+        description = "Workspace ID or name matcher, all workspaces if unset";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      start_time = lib.mkOption {
+        description = "When to start applying the shader, needs end_time";
+        type = lib.types.nullOr time;
+        default = null;
+      };
+      end_time = lib.mkOption {
+        description = "When to stop applying the shader, needs start_time";
+        type = lib.types.nullOr time;
+        default = null;
+      };
+    } // windowConditionOptions;
   };
 
+  percent =
+    min: max: description:
+    lib.mkOption {
+      description = "${description} in percent (${toString min}-${toString max})";
+      type = lib.types.nullOr (lib.types.ints.between min max);
+      default = null;
+    };
+
+  ruleSubmodule = lib.types.submodule {
+    options = {
+      priority = lib.mkOption {
+        description = "Rules with a higher priority override lower ones setting the same effect";
+        type = lib.types.int;
+        default = 0;
+      };
+      window_class = lib.mkOption {
+        description = "Window class regex, or { exact | glob | regex | not = ...; }";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      window_title = lib.mkOption {
+        description = "Window title regex, or { exact | glob | regex | not = ...; }";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      workspace = lib.mkOption {
+        description = "Workspace ID or name matcher, all workspaces if unset";
+        type = lib.types.nullOr matcherType;
+        default = null;
+      };
+      monitor = lib.mkOption {
+        description = "Monitor name or description regex, all monitors if unset";
+        type = lib.types.nullOr lib.types.str;
+        default = null;
+      };
+      start_time = lib.mkOption {
+        description = "When the rule starts applying, needs end_time";
+        type = lib.types.nullOr time;
+        default = null;
+      };
+      end_time = lib.mkOption {
+        description = "When the rule stops applying, needs start_time";
+        type = lib.types.nullOr time;
+        default = null;
+      };
+      temperature = lib.mkOption {
+        description = "Temperature in kelvin (1000-40000)";
+        type = lib.types.nullOr (lib.types.ints.between 1000 40000);
+        default = null;
+      };
+      vibrance = lib.mkOption {
+        description = "Vibrance strength";
+        type = lib.types.nullOr lib.types.int;
+        default = null;
+      };
+      brightness = percent 1 100 "Brightness";
+      gamma = percent 10 500 "Gamma";
+      contrast = percent 1 300 "Contrast";
+      custom_shader = lib.mkOption {
+        description = "Custom GLSL color transform";
+        type = lib.types.nullOr (lib.types.submodule { options = shaderSourceOptions; });
+        default = null;
+      };
+    } // windowConditionOptions;
+  };
+
+  removeNulls = lib.attrsets.filterAttrs (n: v: v != null);
+
   cfg = config.programs.hyprlux;
   cfgFormat = pkgs.formats.toml { };
 
@@ -121,12 +325,71 @@ in
       ];
     };
 
+    custom_shaders = lib.mkOption {
+      description = "Custom GLSL color transforms";
+      type = lib.types.listOf customShaderSubmodule;
+      default = [ ];
+      example = [
+        {
+          name = "grayscale";
+          path = "/etc/hyprlux/shaders/grayscale.glsl";
+          vars = {
+            strength = "0.8";
+          };
+          window_class = "^(code)$";
+          start_time = "22:00";
+          end_time = "06:00";
+        }
+      ];
+    };
+
+    rules = lib.mkOption {
+      description = "Rules applying effects while their conditions hold, for each effect the highest priority matching rule wins";
+      type = lib.types.listOf ruleSubmodule;
+      default = [ ];
+      example = [
+        {
+          priority = 10;
+          window_class = {
+            glob = "steam_app_*";
+          };
+          monitor = "DP-1";
+          start_time = "22:00";
+          end_time = "06:00";
+          temperature = 5000;
+          vibrance = 100;
+          brightness = 70;
+        }
+      ];
+    };
+
     hot_reload = lib.mkOption {
       description = "Listen for config changes";
       type = lib.types.bool;
       default = false;
       example = true;
     };
+
+    notify_errors = lib.mkOption {
+      description = "Show a Hyprland notification when the config is invalid";
+      type = lib.types.bool;
+      default = false;
+      example = true;
+    };
+
+    compose_baseline = lib.mkOption {
+      description = "Apply effects on top of the screen shader found at startup instead of replacing it";
+      type = lib.types.bool;
+      default = false;
+      example = true;
+    };
+
+    respect_external_shader = lib.mkOption {
+      description = "Leave a screen shader set by another tool while hyprlux runs alone instead of replacing it";
+      type = lib.types.bool;
+      default = false;
+      example = true;
+    };
   };
 
   config = lib.mkIf cfg.enable {
@@ -136,9 +399,18 @@ in
 
     environment.etc."hyprlux/config.toml" = {
       source = cfgFormat.generate "config.toml" {
-        night_light = lib.attrsets.filterAttrs (n: v: v != null) cfg.night_light;
-        vibrance_configs = cfg.vibrance_configs;
+        night_light = removeNulls (
+          cfg.night_light // { exclude = map removeNulls cfg.night_light.exclude; }
+        );
+        vibrance_configs = map removeNulls cfg.vibrance_configs;
+        custom_shaders = map removeNulls cfg.custom_shaders;
+        rules = map (
+          rule: removeNulls (rule // { custom_shader = lib.mapNullable removeNulls rule.custom_shader; })
+        ) cfg.rules;
         hot_reload = cfg.hot_reload;
+        notify_errors = cfg.notify_errors;
+        respect_external_shader = cfg.respect_external_shader;
+        compose_baseline = cfg.compose_baseline;
       };
     };
   };
@@ -0,0 +1,76 @@
+use std::fs;
+use std::sync::Mutex;
+
+use super::super::shaders::monitor::MonitorInfo;
+use super::super::shaders::window_context::WindowContext;
+use super::{Compositor, Event};
+
+/// In-memory compositor for tests, recording every screen shader set on it.
+#[derive(Default)]
+pub struct FakeCompositor {
+    shaders: Mutex<Vec<Option<String>>>,
+    active_window: Mutex<WindowContext>,
+    monitors: Mutex<Vec<MonitorInfo>>,
+    events: Mutex<Vec<Event>>,
+}
+
+pub fn new() -> FakeCompositor {
+    FakeCompositor::default()
+}
+
+impl FakeCompositor {
+    pub fn set_active_window(&self, context: WindowContext) {
+        *self.active_window.lock().unwrap() = context;
+    }
+
+    pub fn set_monitors(&self, monitors: Vec<MonitorInfo>) {
+        *self.monitors.lock().unwrap() = monitors;
+    }
+
+    /// Queues an event for the next `listen`.
+    pub fn push_event(&self, event: Event) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// Every screen shader path set so far, `None` where the shader was removed.
+    pub fn history(&self) -> Vec<Option<String>> {
+        self.shaders.lock().unwrap().clone()
+    }
+
+    /// Source of the current screen shader.
+    pub fn shader_source(&self) -> Option<String> {
+        let path = self.shaders.lock().unwrap().last().cloned().flatten()?;
+
+        fs::read_to_string(path).ok()
+    }
+}
+
+impl Compositor for FakeCompositor {
+    fn set_screen_shader(&self, path: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        self.shaders.lock().unwrap().push(path.map(str::to_string));
+        Ok(())
+    }
+
+    fn screen_shader(&self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        Ok(self.shaders.lock().unwrap().last().cloned().flatten())
+    }
+
+    fn active_window(&self) -> Result<WindowContext, Box<dyn std::error::Error>> {
+        Ok(self.active_window.lock().unwrap().clone())
+    }
+
+    fn monitors(&self) -> Result<Vec<MonitorInfo>, Box<dyn std::error::Error>> {
+        Ok(self.monitors.lock().unwrap().clone())
+    }
+
+    /// Calls `handler` for the queued events, then returns as if the stream ended.
+    fn listen(
+        &self,
+        handler: Box<dyn Fn(Event) + Send + Sync>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let events: Vec<Event> = self.events.lock().unwrap().drain(..).collect();
+        events.into_iter().for_each(handler);
+
+        Ok(())
+    }
+}
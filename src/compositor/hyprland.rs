@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use hyprland::event_listener::EventListener;
+use hyprland::keyword::Keyword;
+use log::debug;
+
+use super::super::shaders::monitor::{self, MonitorInfo};
+use super::super::shaders::window_context::{self, WindowContext};
+use super::{Compositor, Event};
+
+const SHADER_KEY: &str = "decoration:screen_shader";
+const NO_SHADER: &str = "[[EMPTY]]";
+
+/// Hyprland over its IPC sockets.
+pub struct Hyprland;
+
+pub fn new() -> Hyprland {
+    Hyprland
+}
+
+impl Compositor for Hyprland {
+    fn set_screen_shader(&self, path: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(Keyword::set(SHADER_KEY, path.unwrap_or(NO_SHADER))?)
+    }
+
+    fn screen_shader(&self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let value = Keyword::get(SHADER_KEY)?.value.to_string();
+
+        Ok(match value.as_str() {
+            NO_SHADER | "" => None,
+            _ => Some(value),
+        })
+    }
+
+    fn active_window(&self) -> Result<WindowContext, Box<dyn std::error::Error>> {
+        Ok(window_context::get_active()?)
+    }
+
+    fn monitors(&self) -> Result<Vec<MonitorInfo>, Box<dyn std::error::Error>> {
+        Ok(monitor::get()?)
+    }
+
+    fn listen(
+        &self,
+        handler: Box<dyn Fn(Event) + Send + Sync>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let handler: Arc<dyn Fn(Event) + Send + Sync> = Arc::from(handler);
+        let mut event_listener = EventListener::new();
+
+        let handler_clone = Arc::clone(&handler);
+        event_listener.add_active_window_changed_handler(move |data| {
+            if data.is_none() {
+                debug!("No active window, evaluating shaders without window context");
+            }
+            handler_clone(Event::ActiveWindowChanged);
+        });
+
+        // Workspace rules may match regardless of the focused window
+        let handler_clone = Arc::clone(&handler);
+        event_listener.add_workspace_changed_handler(move |data| {
+            debug!("Workspace changed: {}", data.id);
+            handler_clone(Event::WorkspaceChanged);
+        });
+
+        // Rules may depend on the window being fullscreen, floating or pinned
+        let handler_clone = Arc::clone(&handler);
+        event_listener.add_fullscreen_state_changed_handler(move |fullscreen| {
+            debug!("Fullscreen state changed: {}", fullscreen);
+            handler_clone(Event::WindowStateChanged);
+        });
+
+        let handler_clone = Arc::clone(&handler);
+        event_listener.add_float_state_changed_handler(move |_| {
+            handler_clone(Event::WindowStateChanged);
+        });
+
+        let handler_clone = Arc::clone(&handler);
+        event_listener.add_window_pinned_handler(move |_| {
+            handler_clone(Event::WindowStateChanged);
+        });
+
+        let handler_clone = Arc::clone(&handler);
+        event_listener.add_config_reloaded_handler(move || {
+            handler_clone(Event::ConfigReloaded);
+        });
+
+        let handler_clone = Arc::clone(&handler);
+        event_listener.add_monitor_added_handler(move |data| {
+            handler_clone(Event::MonitorAdded(data.name));
+        });
+
+        event_listener.add_monitor_removed_handler(move |name| {
+            handler(Event::MonitorRemoved(name));
+        });
+
+        Ok(event_listener.start_listener()?)
+    }
+}
@@ -0,0 +1,33 @@
+pub mod fake;
+pub mod hyprland;
+
+use super::shaders::monitor::MonitorInfo;
+use super::shaders::window_context::WindowContext;
+
+/// Compositor events that may change which shaders apply.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    ActiveWindowChanged,
+    WorkspaceChanged,
+    /// The focused window became fullscreen, floating or pinned, or stopped being so.
+    WindowStateChanged,
+    /// Hyprland reloaded its config, resetting the screen shader.
+    ConfigReloaded,
+    MonitorAdded(String),
+    MonitorRemoved(String),
+}
+
+/// The compositor hyprlux applies shaders to.
+pub trait Compositor: Send + Sync {
+    /// Sets the screen shader to the file at `path`, or removes it for `None`.
+    fn set_screen_shader(&self, path: Option<&str>) -> Result<(), Box<dyn std::error::Error>>;
+    /// Path of the current screen shader, `None` if there is none.
+    fn screen_shader(&self) -> Result<Option<String>, Box<dyn std::error::Error>>;
+    fn active_window(&self) -> Result<WindowContext, Box<dyn std::error::Error>>;
+    fn monitors(&self) -> Result<Vec<MonitorInfo>, Box<dyn std::error::Error>>;
+    /// Calls `handler` for each event, blocking until the event stream ends.
+    fn listen(
+        &self,
+        handler: Box<dyn Fn(Event) + Send + Sync>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+}
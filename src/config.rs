@@ -1,14 +1,35 @@
+use chrono::NaiveTime;
+use hyprlux::shaders::adjustment::Adjustment;
+use hyprlux::shaders::matcher::{self, Matcher};
+use hyprlux::shaders::night_light;
+use hyprlux::shaders::window_rule::Combinator;
 use log::{error, info};
-use serde::Deserialize;
-use std::{env, fs};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::{env, fmt, fs, io};
+use sunrise::Coordinates;
+use toml_edit::ImDocument;
 
 const DEFAULT_CONFIG_PATH: &str = "/etc/hyprlux/config.toml";
+const TIME_FMT: &str = "%H:%M";
+pub const MIN_TEMPERATURE: i32 = 1000;
+pub const MAX_TEMPERATURE: i32 = 40000;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Config {
     pub night_light: NightLightConfig,
     pub vibrance_configs: Vec<VibranceConfig>,
+    pub custom_shaders: Option<Vec<CustomShaderConfig>>,
+    pub rules: Option<Vec<RuleConfig>>,
     pub hot_reload: Option<bool>,
+    /// Show a Hyprland notification when the config is invalid
+    pub notify_errors: Option<bool>,
+    /// Leave screen shaders set by something else alone
+    pub respect_external_shader: Option<bool>,
+    /// Apply effects on top of the screen shader found at startup
+    pub compose_baseline: Option<bool>,
 }
 
 impl Default for Config {
@@ -16,12 +37,18 @@ impl Default for Config {
         Self {
             night_light: NightLightConfig::default(),
             vibrance_configs: Vec::new(),
+            custom_shaders: None,
+            rules: None,
             hot_reload: Some(false),
+            notify_errors: Some(false),
+            respect_external_shader: Some(false),
+            compose_baseline: Some(false),
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct NightLightConfig {
     pub enabled: bool,
     pub start_time: Option<String>,
@@ -29,6 +56,14 @@ pub struct NightLightConfig {
     pub temperature: i32,
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
+    /// Minutes over which the temperature fades in and out
+    pub transition_duration: Option<u64>,
+    /// Temperature increment in kelvin between transition shaders
+    pub transition_step: Option<i32>,
+    /// Windows that suppress night light while focused
+    pub exclude: Option<Vec<WindowRuleConfig>>,
+    /// Monitor name or description regex, night light applies to all monitors if unset
+    pub monitor: Option<String>,
 }
 
 impl Default for NightLightConfig {
@@ -40,27 +75,301 @@ impl Default for NightLightConfig {
             temperature: 3500,
             latitude: None,
             longitude: None,
+            transition_duration: None,
+            transition_step: None,
+            exclude: None,
+            monitor: None,
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct VibranceConfig {
-    pub window_class: String,
-    pub window_title: String,
+    pub window_class: Option<MatcherConfig>,
+    pub window_title: Option<MatcherConfig>,
     pub strength: i32,
+    /// Workspace ID or name matcher, the rule applies on all workspaces if unset
+    pub workspace: Option<MatcherConfig>,
+    #[serde(flatten)]
+    pub conditions: WindowConditionsConfig,
+    /// Monitor name or description regex, the rule applies to all monitors if unset
+    pub monitor: Option<String>,
 }
 
 impl Default for VibranceConfig {
     fn default() -> Self {
         Self {
-            window_class: "".to_string(),
-            window_title: "".to_string(),
-            strength: 0,
+            window_class: None,
+            window_title: None,
+            strength: 100,
+            workspace: None,
+            conditions: WindowConditionsConfig::default(),
+            monitor: None,
         }
     }
 }
 
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CustomShaderConfig {
+    #[serde(flatten)]
+    pub shader: ShaderSourceConfig,
+    pub window_class: Option<MatcherConfig>,
+    pub window_title: Option<MatcherConfig>,
+    pub workspace: Option<MatcherConfig>,
+    #[serde(flatten)]
+    pub conditions: WindowConditionsConfig,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+}
+
+/// A custom GLSL shader, either on its own or as the effect of a rule
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ShaderSourceConfig {
+    pub name: String,
+    /// Path to a GLSL file, takes precedence over `source`
+    pub path: Option<String>,
+    /// Inline GLSL source
+    pub source: Option<String>,
+    /// Values for the `{placeholders}` in the source
+    pub vars: Option<HashMap<String, String>>,
+}
+
+/// Effects applied while the conditions hold. `night_light`, `vibrance_configs` and
+/// `custom_shaders` are shorthands for rules with priority 0.
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RuleConfig {
+    /// Rules with a higher priority override lower ones setting the same effect
+    pub priority: i32,
+    pub window_class: Option<MatcherConfig>,
+    pub window_title: Option<MatcherConfig>,
+    pub workspace: Option<MatcherConfig>,
+    #[serde(flatten)]
+    pub conditions: WindowConditionsConfig,
+    /// Monitor name or description regex, the rule applies to all monitors if unset
+    pub monitor: Option<String>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// Temperature in kelvin
+    pub temperature: Option<i32>,
+    /// Vibrance strength
+    pub vibrance: Option<i32>,
+    /// Brightness in percent (1-100)
+    pub brightness: Option<i32>,
+    /// Gamma in percent (10-500), 100 leaves the colors unchanged
+    pub gamma: Option<i32>,
+    /// Contrast in percent (1-300), 100 leaves the colors unchanged
+    pub contrast: Option<i32>,
+    pub custom_shader: Option<ShaderSourceConfig>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowRuleConfig {
+    pub window_class: Option<MatcherConfig>,
+    pub window_title: Option<MatcherConfig>,
+    pub workspace: Option<MatcherConfig>,
+    #[serde(flatten)]
+    pub conditions: WindowConditionsConfig,
+}
+
+/// Properties the focused window must have, unset ones match any window
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowConditionsConfig {
+    /// How `window_class` and `window_title` combine, `all` if unset
+    #[serde(rename = "match")]
+    pub combinator: Option<CombinatorConfig>,
+    /// Matcher for the class the window was created with
+    pub initial_class: Option<MatcherConfig>,
+    /// Matcher for the title the window was created with
+    pub initial_title: Option<MatcherConfig>,
+    pub pid: Option<i32>,
+    pub fullscreen: Option<bool>,
+    pub floating: Option<bool>,
+    pub xwayland: Option<bool>,
+    pub pinned: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CombinatorConfig {
+    All,
+    Any,
+}
+
+impl From<CombinatorConfig> for Combinator {
+    fn from(combinator: CombinatorConfig) -> Self {
+        match combinator {
+            CombinatorConfig::All => Combinator::All,
+            CombinatorConfig::Any => Combinator::Any,
+        }
+    }
+}
+
+/// A plain string is a regex, an empty one is the same as leaving the matcher unset.
+/// Otherwise a table with one of `exact`, `glob`, `regex` or `not`, e.g.
+/// `{ not = { glob = "steam_app_*" } }`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MatcherConfig {
+    Regex(String),
+    Mode(MatchModeConfig),
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchModeConfig {
+    Exact(String),
+    Glob(String),
+    Regex(String),
+    Not(Box<MatcherConfig>),
+}
+
+impl MatcherConfig {
+    fn compile(&self) -> Result<Matcher, regex::Error> {
+        match self {
+            MatcherConfig::Regex(pattern) => matcher::regex(pattern),
+            MatcherConfig::Mode(MatchModeConfig::Exact(value)) => matcher::exact(value),
+            MatcherConfig::Mode(MatchModeConfig::Glob(pattern)) => matcher::glob(pattern),
+            MatcherConfig::Mode(MatchModeConfig::Regex(pattern)) => matcher::regex(pattern),
+            MatcherConfig::Mode(MatchModeConfig::Not(config)) => {
+                Ok(matcher::not(config.compile()?))
+            }
+        }
+    }
+}
+
+/// Compiles an optional matcher, `None` if it's unset or an empty plain string.
+pub fn matcher(config: &Option<MatcherConfig>) -> Result<Option<Matcher>, regex::Error> {
+    match config {
+        None => Ok(None),
+        Some(MatcherConfig::Regex(pattern)) if pattern.is_empty() => Ok(None),
+        Some(config) => config.compile().map(Some),
+    }
+}
+
+/// Documentation for every config field as (key, description, example). The example is
+/// shown commented out when the field has no default value.
+const FIELD_DOCS: &[(&str, &str, &str)] = &[
+    ("hot_reload", "Reload the config when the file changes", ""),
+    (
+        "notify_errors",
+        "Show a Hyprland notification when the config is invalid",
+        "",
+    ),
+    (
+        "respect_external_shader",
+        "Leave a screen shader set by another tool while hyprlux runs alone instead of replacing it",
+        "",
+    ),
+    (
+        "compose_baseline",
+        "Apply effects on top of the screen shader found at startup instead of replacing it. That shader is restored whenever no effects apply",
+        "",
+    ),
+    (
+        "vibrance_configs",
+        "Vibrance rules, on each monitor the first one matching the active window (class, title, initial_class, initial_title and workspace matchers, pid, fullscreen, floating, xwayland and pinned) is applied. Matchers are regexes or { exact | glob | regex | not = ... }, class and title combine with match = \"all\" (default) or \"any\"",
+        r#"[{ window_class = { glob = "steam_app_*" }, window_title = { not = { exact = "Launcher" } }, strength = 100, monitor = "DP-1" }]"#,
+    ),
+    (
+        "custom_shaders",
+        "Custom GLSL color transforms defining `vec3 {name}(vec3 color)`, filled from `vars`",
+        r#"[{ name = "grayscale", path = "/etc/hyprlux/shaders/grayscale.glsl", vars = { strength = "0.8" }, window_class = "^(code)$", start_time = "22:00", end_time = "06:00" }]"#,
+    ),
+    (
+        "rules",
+        "Rules with conditions (window matchers, workspace, monitor, start_time/end_time) and effects (temperature, vibrance, brightness 1-100, gamma 10-500 and contrast 1-300 in percent, and custom_shader). For each effect, a monitor gets it from the highest priority matching rule, the sections above are rules with priority 0",
+        r#"[{ priority = 10, window_class = { glob = "steam_app_*" }, monitor = "DP-1", start_time = "22:00", end_time = "06:00", temperature = 5000, vibrance = 100, brightness = 70 }]"#,
+    ),
+    ("night_light.enabled", "Enable night light", ""),
+    (
+        "night_light.start_time",
+        "When to start night light (HH:MM), used without a location",
+        r#""20:00""#,
+    ),
+    (
+        "night_light.end_time",
+        "When to end night light (HH:MM), used without a location",
+        r#""06:00""#,
+    ),
+    (
+        "night_light.temperature",
+        "Night light temperature in kelvin (1000-40000)",
+        "",
+    ),
+    (
+        "night_light.latitude",
+        "Latitude used to start night light at sunset and end it at sunrise",
+        "46.056946",
+    ),
+    (
+        "night_light.longitude",
+        "Longitude used to start night light at sunset and end it at sunrise",
+        "14.505751",
+    ),
+    (
+        "night_light.transition_duration",
+        "Minutes over which the temperature fades in and out, at most 1440",
+        "45",
+    ),
+    (
+        "night_light.transition_step",
+        "Temperature step in kelvin used while transitioning",
+        "100",
+    ),
+    (
+        "night_light.exclude",
+        "Windows that suppress night light while focused",
+        r#"[{ window_class = "^(gimp)(.*)$", window_title = "" }]"#,
+    ),
+    (
+        "night_light.monitor",
+        "Monitor name or description regex to limit night light to",
+        r#""^(HDMI-A-1)$""#,
+    ),
+];
+
+/// Renders the default config as TOML with every field documented.
+pub fn dump_defaults() -> String {
+    let defaults = toml::Table::try_from(Config::default()).unwrap();
+    let mut output =
+        "# Hyprlux configuration, generated by `hyprlux config dump-defaults`\n".to_string();
+    let mut current_section = "";
+
+    for (key, description, example) in FIELD_DOCS {
+        let (section, field) = key.rsplit_once('.').unwrap_or(("", key));
+        let table = match section {
+            "" => Some(&defaults),
+            section => defaults.get(section).and_then(|value| value.as_table()),
+        };
+
+        if section != current_section {
+            output.push_str(&format!("\n[{}]\n", section));
+            current_section = section;
+        } else {
+            output.push('\n');
+        }
+
+        output.push_str(&format!("# {}\n", description));
+        match table.and_then(|table| table.get(field)) {
+            Some(value) => output.push_str(&format!("{} = {}\n", field, value)),
+            None => output.push_str(&format!("# {} = {}\n", field, example)),
+        }
+    }
+
+    output
+}
+
+pub const USAGE: &str = "Usage: hyprlux config <command>
+
+Commands:
+    dump-defaults             Print the default config with every field documented";
+
 pub fn path() -> String {
     let args: Vec<String> = env::args().collect();
     if args.len() > 1 {
@@ -81,16 +390,654 @@ pub fn path() -> String {
     DEFAULT_CONFIG_PATH.to_string()
 }
 
-pub fn load(config_path: String) -> Option<Config> {
+/// Reads and validates the config file, returning `None` if it doesn't exist or is empty.
+pub fn load(config_path: String) -> Result<Option<Config>, ConfigError> {
     info!("Loading config file at {}", &config_path);
 
-    let contents = fs::read_to_string(config_path).unwrap_or("".to_string());
+    let contents = match fs::read_to_string(&config_path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => "".to_string(),
+        Err(error) => {
+            return Err(ConfigError::Read {
+                path: config_path,
+                source: error,
+            })
+        }
+    };
 
     // Return default config if no config file exists
     if contents.is_empty() {
         error!("No config file found. Using default config.");
-        return None;
+        return Ok(None);
+    }
+
+    parse(&config_path, &contents).map(Some)
+}
+
+pub fn parse(config_path: &str, contents: &str) -> Result<Config, ConfigError> {
+    let config: Config = toml::from_str(contents).map_err(|error| ConfigError::Parse {
+        path: config_path.to_string(),
+        line: error.span().map(|span| line_at(contents, span.start)),
+        message: error.message().to_string(),
+    })?;
+
+    config
+        .validate()
+        .map_err(|(field, message)| ConfigError::Invalid {
+            path: config_path.to_string(),
+            line: line_of(contents, &field),
+            field,
+            message,
+        })?;
+
+    Ok(config)
+}
+
+/// Error returned as (field, message).
+type ValidationError = (String, String);
+
+impl Config {
+    fn validate(&self) -> Result<(), ValidationError> {
+        let night_light = &self.night_light;
+        validate_schedule(
+            "night_light",
+            &night_light.start_time,
+            &night_light.end_time,
+        )?;
+
+        validate_range(
+            "night_light.temperature",
+            Some(night_light.temperature),
+            MIN_TEMPERATURE,
+            MAX_TEMPERATURE,
+        )?;
+        let message = "latitude and longitude must be set together".to_string();
+        match (night_light.latitude, night_light.longitude) {
+            (Some(latitude), Some(longitude)) => {
+                if Coordinates::new(latitude, longitude).is_none() {
+                    return Err((
+                        "night_light.latitude".to_string(),
+                        "invalid coordinates".to_string(),
+                    ));
+                }
+            }
+            (Some(_), None) => return Err(("night_light.latitude".to_string(), message)),
+            (None, Some(_)) => return Err(("night_light.longitude".to_string(), message)),
+            (None, None) => {}
+        }
+        let max_transition = night_light::MAX_TRANSITION.as_secs() / 60;
+        if let Some(duration) = night_light.transition_duration {
+            if duration > max_transition {
+                return Err((
+                    "night_light.transition_duration".to_string(),
+                    format!("must be at most {} minutes", max_transition),
+                ));
+            }
+        }
+        if let Some(step) = night_light.transition_step {
+            if step <= 0 {
+                return Err((
+                    "night_light.transition_step".to_string(),
+                    "must be positive".to_string(),
+                ));
+            }
+        }
+        for (i, rule) in night_light.exclude.iter().flatten().enumerate() {
+            validate_window(
+                &format!("night_light.exclude[{}]", i),
+                [&rule.window_class, &rule.window_title, &rule.workspace],
+                &rule.conditions,
+            )?;
+        }
+        if let Some(monitor) = &night_light.monitor {
+            validate_regex("night_light.monitor", monitor)?;
+        }
+
+        for (i, vibrance) in self.vibrance_configs.iter().enumerate() {
+            let field = format!("vibrance_configs[{}]", i);
+            validate_window(
+                &field,
+                [
+                    &vibrance.window_class,
+                    &vibrance.window_title,
+                    &vibrance.workspace,
+                ],
+                &vibrance.conditions,
+            )?;
+            if let Some(monitor) = &vibrance.monitor {
+                validate_regex(&format!("{}.monitor", field), monitor)?;
+            }
+        }
+
+        for (i, custom) in self.custom_shaders.iter().flatten().enumerate() {
+            let field = format!("custom_shaders[{}]", i);
+            validate_shader_source(&field, &custom.shader)?;
+            validate_window(
+                &field,
+                [
+                    &custom.window_class,
+                    &custom.window_title,
+                    &custom.workspace,
+                ],
+                &custom.conditions,
+            )?;
+            validate_schedule(&field, &custom.start_time, &custom.end_time)?;
+        }
+
+        for (i, rule) in self.rules.iter().flatten().enumerate() {
+            let field = format!("rules[{}]", i);
+            validate_window(
+                &field,
+                [&rule.window_class, &rule.window_title, &rule.workspace],
+                &rule.conditions,
+            )?;
+            if let Some(monitor) = &rule.monitor {
+                validate_regex(&format!("{}.monitor", field), monitor)?;
+            }
+            validate_schedule(&field, &rule.start_time, &rule.end_time)?;
+            validate_range(
+                &format!("{}.temperature", field),
+                rule.temperature,
+                MIN_TEMPERATURE,
+                MAX_TEMPERATURE,
+            )?;
+            for (adjustment, value) in [
+                (Adjustment::Brightness, rule.brightness),
+                (Adjustment::Gamma, rule.gamma),
+                (Adjustment::Contrast, rule.contrast),
+            ] {
+                let (min, max) = adjustment.range();
+                validate_range(&format!("{}.{}", field, adjustment.name()), value, min, max)?;
+            }
+            if let Some(shader) = &rule.custom_shader {
+                validate_shader_source(&format!("{}.custom_shader", field), shader)?;
+            }
+            let effects = [
+                rule.temperature,
+                rule.vibrance,
+                rule.brightness,
+                rule.gamma,
+                rule.contrast,
+            ];
+            if effects.iter().all(Option::is_none) && rule.custom_shader.is_none() {
+                return Err((
+                    field,
+                    "needs an effect: temperature, vibrance, brightness, gamma, contrast or custom_shader".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_time(field: &str, value: &Option<String>) -> Result<(), ValidationError> {
+    match value {
+        Some(value) if NaiveTime::parse_from_str(value, TIME_FMT).is_err() => Err((
+            field.to_string(),
+            format!("invalid time \"{}\", expected HH:MM", value),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn validate_range(
+    field: &str,
+    value: Option<i32>,
+    min: i32,
+    max: i32,
+) -> Result<(), ValidationError> {
+    match value {
+        Some(value) if !(min..=max).contains(&value) => Err((
+            field.to_string(),
+            format!("must be between {} and {}", min, max),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Validates an optional time window, which needs both ends or neither.
+fn validate_schedule(
+    field: &str,
+    start_time: &Option<String>,
+    end_time: &Option<String>,
+) -> Result<(), ValidationError> {
+    validate_time(&format!("{}.start_time", field), start_time)?;
+    validate_time(&format!("{}.end_time", field), end_time)?;
+
+    let message = "start_time and end_time must be set together".to_string();
+    match (start_time, end_time) {
+        (Some(_), None) => Err((format!("{}.start_time", field), message)),
+        (None, Some(_)) => Err((format!("{}.end_time", field), message)),
+        _ => Ok(()),
+    }
+}
+
+fn validate_regex(field: &str, value: &str) -> Result<(), ValidationError> {
+    match Regex::new(value) {
+        Ok(_) => Ok(()),
+        Err(error) => Err((field.to_string(), error.to_string())),
+    }
+}
+
+fn validate_matcher(field: &str, config: &Option<MatcherConfig>) -> Result<(), ValidationError> {
+    matcher(config)
+        .map(|_| ())
+        .map_err(|error| (field.to_string(), error.to_string()))
+}
+
+fn validate_shader_source(field: &str, shader: &ShaderSourceConfig) -> Result<(), ValidationError> {
+    if shader.path.is_none() && shader.source.is_none() {
+        return Err((
+            field.to_string(),
+            "either path or source is required".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Validates the class, title and workspace matchers and the conditions of a rule.
+fn validate_window(
+    field: &str,
+    [window_class, window_title, workspace]: [&Option<MatcherConfig>; 3],
+    conditions: &WindowConditionsConfig,
+) -> Result<(), ValidationError> {
+    validate_matcher(&format!("{}.window_class", field), window_class)?;
+    validate_matcher(&format!("{}.window_title", field), window_title)?;
+    validate_matcher(&format!("{}.workspace", field), workspace)?;
+    validate_conditions(field, conditions)
+}
+
+fn validate_conditions(
+    field: &str,
+    conditions: &WindowConditionsConfig,
+) -> Result<(), ValidationError> {
+    validate_matcher(
+        &format!("{}.initial_class", field),
+        &conditions.initial_class,
+    )?;
+    validate_matcher(
+        &format!("{}.initial_title", field),
+        &conditions.initial_title,
+    )
+}
+
+/// 1-based line number of the byte offset.
+fn line_at(contents: &str, offset: usize) -> usize {
+    contents[..offset.min(contents.len())].matches('\n').count() + 1
+}
+
+/// 1-based line number of the value at a field path like `rules[1].temperature`, or of the
+/// closest table containing it.
+fn line_of(contents: &str, field: &str) -> Option<usize> {
+    let document = ImDocument::parse(contents).ok()?;
+    let mut item = document.as_item();
+    let mut span = None;
+
+    for part in field.split('.') {
+        let (key, index) = match part.strip_suffix(']').and_then(|part| part.split_once('[')) {
+            Some((key, index)) => (key, index.parse::<usize>().ok()),
+            None => (part, None),
+        };
+
+        match item.get(key) {
+            Some(value) => item = value,
+            None => break,
+        }
+        span = item.span().or(span);
+
+        if let Some(index) = index {
+            match item.get(index) {
+                Some(value) => item = value,
+                None => break,
+            }
+            span = item.span().or(span);
+        }
     }
 
-    Some(toml::from_str(&contents).unwrap())
+    span.map(|span| line_at(contents, span.start))
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read {
+        path: String,
+        source: io::Error,
+    },
+    Parse {
+        path: String,
+        line: Option<usize>,
+        message: String,
+    },
+    Invalid {
+        path: String,
+        line: Option<usize>,
+        field: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = |path: &str, line: &Option<usize>| match line {
+            Some(line) => format!("{}:{}", path, line),
+            None => path.to_string(),
+        };
+
+        match self {
+            ConfigError::Read { path, source } => write!(f, "{}: {}", path, source),
+            ConfigError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}: {}", location(path, line), message),
+            ConfigError::Invalid {
+                path,
+                line,
+                field,
+                message,
+            } => write!(f, "{}: {}: {}", location(path, line), field, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Read { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "hyprlux.toml";
+
+    #[test]
+    fn test_parse() {
+        let config = parse(
+            PATH,
+            r#"
+night_light = { enabled = true, start_time = "20:00", end_time = "06:00", temperature = 3500 }
+
+vibrance_configs = [
+  { window_class = "^(steam_app_)(.*)$", window_title = "", strength = 100 },
+]
+"#,
+        )
+        .unwrap();
+
+        assert!(config.night_light.enabled);
+        assert_eq!(config.vibrance_configs.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let configs = [
+            ("", Config::default()),
+            (
+                "night_light = { enabled = true, start_time = \"20:00\", end_time = \"06:00\" }",
+                Config {
+                    night_light: NightLightConfig {
+                        enabled: true,
+                        start_time: Some("20:00".to_string()),
+                        end_time: Some("06:00".to_string()),
+                        ..NightLightConfig::default()
+                    },
+                    ..Config::default()
+                },
+            ),
+            (
+                "vibrance_configs = [{ window_class = \"firefox\", fullscreen = true }]",
+                Config {
+                    vibrance_configs: vec![VibranceConfig {
+                        window_class: Some(MatcherConfig::Regex("firefox".to_string())),
+                        conditions: WindowConditionsConfig {
+                            fullscreen: Some(true),
+                            ..WindowConditionsConfig::default()
+                        },
+                        ..VibranceConfig::default()
+                    }],
+                    ..Config::default()
+                },
+            ),
+            (
+                "vibrance_configs = [{ window_class = \"cs2\" }]",
+                Config {
+                    vibrance_configs: vec![VibranceConfig {
+                        window_class: Some(MatcherConfig::Regex("cs2".to_string())),
+                        ..VibranceConfig::default()
+                    }],
+                    ..Config::default()
+                },
+            ),
+            (
+                "vibrance_configs = [{ window_class = { exact = \"cs2\" }, window_title = { not = { glob = \"*Launcher\" } }, match = \"any\" }]",
+                Config {
+                    vibrance_configs: vec![VibranceConfig {
+                        window_class: Some(MatcherConfig::Mode(MatchModeConfig::Exact(
+                            "cs2".to_string(),
+                        ))),
+                        window_title: Some(MatcherConfig::Mode(MatchModeConfig::Not(Box::new(
+                            MatcherConfig::Mode(MatchModeConfig::Glob("*Launcher".to_string())),
+                        )))),
+                        conditions: WindowConditionsConfig {
+                            combinator: Some(CombinatorConfig::Any),
+                            ..WindowConditionsConfig::default()
+                        },
+                        ..VibranceConfig::default()
+                    }],
+                    ..Config::default()
+                },
+            ),
+            (
+                "[[rules]]\npriority = 10\nwindow_class = { glob = \"steam_app_*\" }\nstart_time = \"22:00\"\nend_time = \"06:00\"\nvibrance = 100\ncustom_shader = { name = \"grayscale\", path = \"grayscale.glsl\" }\n",
+                Config {
+                    rules: Some(vec![RuleConfig {
+                        priority: 10,
+                        window_class: Some(MatcherConfig::Mode(MatchModeConfig::Glob(
+                            "steam_app_*".to_string(),
+                        ))),
+                        start_time: Some("22:00".to_string()),
+                        end_time: Some("06:00".to_string()),
+                        vibrance: Some(100),
+                        custom_shader: Some(ShaderSourceConfig {
+                            name: "grayscale".to_string(),
+                            path: Some("grayscale.glsl".to_string()),
+                            ..ShaderSourceConfig::default()
+                        }),
+                        ..RuleConfig::default()
+                    }]),
+                    ..Config::default()
+                },
+            ),
+        ];
+        for (contents, expected) in configs {
+            assert_eq!(parse(PATH, contents).unwrap(), expected, "{}", contents)
+        }
+    }
+
+    #[test]
+    fn test_dump_defaults() {
+        let dump = dump_defaults();
+        assert_eq!(parse(PATH, &dump).unwrap(), Config::default());
+
+        // Every field is documented and every documented field exists. The struct literals
+        // set all fields so that new ones can't be left out.
+        let populated = Config {
+            night_light: NightLightConfig {
+                enabled: true,
+                start_time: Some("20:00".to_string()),
+                end_time: Some("06:00".to_string()),
+                temperature: 3500,
+                latitude: Some(46.056946),
+                longitude: Some(14.505751),
+                transition_duration: Some(45),
+                transition_step: Some(100),
+                exclude: Some(Vec::new()),
+                monitor: Some("DP-1".to_string()),
+            },
+            vibrance_configs: Vec::new(),
+            custom_shaders: Some(Vec::new()),
+            rules: Some(Vec::new()),
+            hot_reload: Some(true),
+            notify_errors: Some(true),
+            respect_external_shader: Some(true),
+            compose_baseline: Some(true),
+        };
+        let fields: Vec<String> = toml::Table::try_from(populated)
+            .unwrap()
+            .iter()
+            .flat_map(|(key, value)| match value.as_table() {
+                Some(table) => table.keys().map(|k| format!("{}.{}", key, k)).collect(),
+                None => vec![key.clone()],
+            })
+            .collect();
+        for field in &fields {
+            assert!(
+                FIELD_DOCS.iter().any(|(doc_key, _, _)| doc_key == field),
+                "{} is not documented",
+                field
+            )
+        }
+        for (doc_key, _, _) in FIELD_DOCS {
+            assert!(
+                fields.iter().any(|field| field == doc_key),
+                "{} is not a field",
+                doc_key
+            )
+        }
+
+        // Unset fields are shown as commented out examples
+        assert!(dump.contains("# start_time = \"20:00\""));
+        assert!(dump.contains("temperature = 3500"));
+
+        // Uncommenting the examples yields a valid config
+        let examples = dump
+            .replace("# start_time", "start_time")
+            .replace("# end_time", "end_time");
+        assert!(parse(PATH, &examples)
+            .unwrap()
+            .night_light
+            .start_time
+            .is_some());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let configs = [
+            (
+                "Syntax error",
+                "night_light = { enabled = true\n",
+                "hyprlux.toml:1: ",
+            ),
+            (
+                "Unknown type",
+                "night_light = { enabled = \"yes\" }\n",
+                "hyprlux.toml:1: invalid type: string \"yes\", expected a boolean",
+            ),
+            (
+                "Invalid start time",
+                "vibrance_configs = []\nnight_light = { enabled = true, start_time = \"25:00\", end_time = \"06:00\", temperature = 3500 }\n",
+                "hyprlux.toml:2: night_light.start_time: invalid time \"25:00\", expected HH:MM",
+            ),
+            (
+                "Invalid temperature",
+                "night_light = { enabled = true, temperature = 100 }\nvibrance_configs = []\n",
+                "hyprlux.toml:1: night_light.temperature: must be between 1000 and 40000",
+            ),
+            (
+                "Invalid coordinates",
+                "night_light = { enabled = true, temperature = 3500, latitude = 123.0, longitude = 14.5 }\nvibrance_configs = []\n",
+                "hyprlux.toml:1: night_light.latitude: invalid coordinates",
+            ),
+            (
+                "Latitude without longitude",
+                "night_light = { enabled = true, temperature = 3500, latitude = 50.0 }\n",
+                "hyprlux.toml:1: night_light.latitude: latitude and longitude must be set together",
+            ),
+            (
+                "Start time without end time",
+                "[night_light]\nenabled = true\nstart_time = \"20:00\"\n",
+                "hyprlux.toml:3: night_light.start_time: start_time and end_time must be set together",
+            ),
+            (
+                "Transition too long",
+                "[night_light]\nenabled = true\ntransition_duration = 1000000000000000000\n",
+                "hyprlux.toml:3: night_light.transition_duration: must be at most 1440 minutes",
+            ),
+            (
+                "Invalid regex",
+                "night_light = { enabled = false, temperature = 3500 }\nvibrance_configs = [\n  { window_class = \"firefox\", window_title = \"\", strength = 100 },\n  { window_class = \"(steam\", window_title = \"\", strength = 100 },\n]\n",
+                "hyprlux.toml:4: vibrance_configs[1].window_class: regex parse error",
+            ),
+            (
+                "Invalid negated regex",
+                "vibrance_configs = [{ window_title = { not = { regex = \"(Launcher\" } } }]\n",
+                "hyprlux.toml:1: vibrance_configs[0].window_title: regex parse error",
+            ),
+            (
+                "Unknown match mode",
+                "vibrance_configs = [{ window_class = { prefix = \"steam\" } }]\n",
+                "hyprlux.toml:1: ",
+            ),
+            (
+                "Rule without effect",
+                "[[rules]]\nwindow_class = \"firefox\"\n",
+                "hyprlux.toml:1: rules[0]: needs an effect",
+            ),
+            (
+                "Invalid rule temperature",
+                "[[rules]]\ntemperature = 100\n",
+                "hyprlux.toml:2: rules[0].temperature: must be between 1000 and 40000",
+            ),
+            (
+                "Rule without start time",
+                "[[rules]]\nvibrance = 100\nend_time = \"06:00\"\n",
+                "hyprlux.toml:3: rules[0].end_time: start_time and end_time must be set together",
+            ),
+            (
+                "Value on an earlier line",
+                "vibrance_configs = [{ window_class = \"steam\", strength = 100 }]\n\n[[rules]]\nvibrance = 50\n\n[[rules]]\ntemperature = 100\n",
+                "hyprlux.toml:7: rules[1].temperature: must be between 1000 and 40000",
+            ),
+            (
+                "Second rule without effect",
+                "[[rules]]\nvibrance = 50\n\n[[rules]]\nwindow_class = \"firefox\"\n",
+                "hyprlux.toml:4: rules[1]: needs an effect",
+            ),
+            (
+                "Invalid rule gamma",
+                "[[rules]]\ngamma = 1000\n",
+                "hyprlux.toml:2: rules[0].gamma: must be between 10 and 500",
+            ),
+            (
+                "Invalid rule contrast",
+                "[[rules]]\nvibrance = 100\ncontrast = 0\n",
+                "hyprlux.toml:3: rules[0].contrast: must be between 1 and 300",
+            ),
+            (
+                "Rule shader without source",
+                "[[rules]]\ncustom_shader = { name = \"grayscale\" }\n",
+                "hyprlux.toml:2: rules[0].custom_shader: either path or source is required",
+            ),
+            (
+                "Custom shader without end time",
+                "[[custom_shaders]]\nname = \"grayscale\"\npath = \"grayscale.frag\"\nstart_time = \"22:00\"\n",
+                "hyprlux.toml:4: custom_shaders[0].start_time: start_time and end_time must be set together",
+            ),
+            (
+                "Invalid monitor regex",
+                "vibrance_configs = [{ window_class = \"cs2\", monitor = \"DP-[\" }]\n",
+                "hyprlux.toml:1: vibrance_configs[0].monitor: regex parse error",
+            ),
+        ];
+        for (name, contents, expected) in configs {
+            let error = parse(PATH, contents).unwrap_err().to_string();
+            assert!(error.contains(expected), "{} - {}", name, error)
+        }
+    }
 }
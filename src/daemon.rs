@@ -0,0 +1,280 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use log::{debug, error, info};
+
+use super::compositor::{Compositor, Event};
+use super::shaders::composite;
+use super::shaders::monitor::MonitorInfo;
+use super::shaders::night_light::NightLightShader;
+use super::shaders::rule::{Effect, Rules};
+use super::shaders::shader::{self, AppliedShader, ScreenShader, Shader};
+use super::shaders::store::ShaderStore;
+use super::shaders::vibrance::VibranceShader;
+use super::shaders::window_context::WindowContext;
+
+pub const TIME_FMT: &str = "%H:%M";
+/// Name of the rule built from the `night_light` config.
+pub const NIGHT_LIGHT_RULE: &str = "night_light";
+
+/// The loaded rules and the runtime state shaders are selected from.
+#[derive(Default, PartialEq)]
+pub struct ConfigData {
+    pub rules: Rules,
+    pub hot_reload: bool,
+    pub notify_errors: bool,
+    /// Leave screen shaders set by something else alone instead of replacing them.
+    pub respect_external_shader: bool,
+    /// Apply effects on top of the baseline shader instead of replacing it.
+    pub compose_baseline: bool,
+    pub paused_until: Option<DateTime<Local>>,
+    pub vibrance_override: Option<VibranceShader>,
+    pub monitors: Vec<MonitorInfo>,
+    pub screen_shader: ScreenShader,
+    /// The screen shader found at startup, restored when no effects apply.
+    pub baseline: Option<String>,
+    pub store: ShaderStore,
+}
+
+impl ConfigData {
+    pub fn is_paused(&self) -> bool {
+        self.paused_until.is_some_and(|until| Local::now() < until)
+    }
+
+    /// The night light from the `night_light` config, which `hyprlux ctl` reports on.
+    pub fn night_light(&self) -> Option<&NightLightShader> {
+        self.rules
+            .named(NIGHT_LIGHT_RULE)?
+            .effects()
+            .iter()
+            .find_map(|effect| match effect {
+                Effect::Temperature(shader) => Some(shader),
+                _ => None,
+            })
+    }
+
+    /// All temperature effects, which `hyprlux ctl` toggles and sets together.
+    pub fn night_lights_mut(&mut self) -> impl Iterator<Item = &mut NightLightShader> {
+        self.rules.effects_mut().filter_map(|effect| match effect {
+            Effect::Temperature(shader) => Some(shader),
+            _ => None,
+        })
+    }
+
+    /// Time until any configured shader may change on its own.
+    pub fn next_change(&self) -> Option<Duration> {
+        let next_change = self.rules.next_change();
+
+        match self.paused_until {
+            Some(until) if self.is_paused() => {
+                let until = (until - Local::now()).to_std().unwrap_or_default();
+                Some(next_change.map_or(until, |next_change| next_change.min(until)))
+            }
+            _ => next_change,
+        }
+    }
+
+    pub fn status(&self) -> String {
+        let night_light = match self.night_light() {
+            Some(shader) if !shader.is_enabled() => "disabled".to_string(),
+            Some(shader) => format!(
+                "enabled, {} ({}K)",
+                if shader.should_apply(&WindowContext::default()) {
+                    "active"
+                } else {
+                    "inactive"
+                },
+                shader.temperature()
+            ),
+            None => "not configured".to_string(),
+        };
+
+        format!(
+            "shader: {}\nnight light: {}\nvibrance override: {}\npaused: {}",
+            self.screen_shader,
+            night_light,
+            self.vibrance_override
+                .as_ref()
+                .map_or("off".to_string(), |shader| shader.hash()),
+            match self.paused_until {
+                Some(until) if self.is_paused() => format!("until {}", until.format(TIME_FMT)),
+                _ => "no".to_string(),
+            }
+        )
+    }
+}
+
+/// Replaces the config with `new_config`, keeping runtime state such as pauses.
+/// Returns whether the config changed.
+pub fn replace_config(config_data: &mut ConfigData, mut new_config: ConfigData) -> bool {
+    new_config.paused_until = config_data.paused_until;
+    new_config.vibrance_override = config_data.vibrance_override.clone();
+    new_config.monitors = config_data.monitors.clone();
+    new_config.screen_shader = config_data.screen_shader.clone();
+    new_config.baseline = config_data.baseline.clone();
+    new_config.store = config_data.store.clone();
+
+    if new_config == *config_data {
+        return false;
+    }
+
+    *config_data = new_config;
+    true
+}
+
+/// Applies the shaders selected for the context, or removes the current one if none apply.
+pub fn apply_shaders(
+    compositor: &dyn Compositor,
+    config_data: &mut ConfigData,
+    context: &WindowContext,
+) {
+    // Paused shaders are removed until the pause expires
+    let shader_to_apply = match config_data.is_paused() {
+        true => composite::new(Vec::new()),
+        false => {
+            // For each effect, each monitor gets it from the highest priority matching rule
+            let overrides: Vec<Effect> = config_data
+                .vibrance_override
+                .iter()
+                .cloned()
+                .map(Effect::Vibrance)
+                .collect();
+            composite::new(
+                config_data
+                    .rules
+                    .select(context, &config_data.monitors, &overrides),
+            )
+        }
+    };
+    let shader_to_apply = match config_data.compose_baseline && !shader_to_apply.is_empty() {
+        true => shader_to_apply.with_baseline(config_data.baseline.clone()),
+        false => shader_to_apply,
+    };
+    let hash = (!shader_to_apply.is_empty()).then(|| shader_to_apply.hash());
+
+    if config_data.screen_shader.is_applied(hash.as_deref()) {
+        return;
+    }
+
+    // Make sure the shader about to be replaced is still hyprlux's own
+    let screen_shader = match compositor.screen_shader() {
+        Ok(path) => config_data
+            .screen_shader
+            .identify(path, config_data.baseline.as_deref()),
+        Err(error) => {
+            error!("Failed to get current shader: {:?}", error);
+            return;
+        }
+    };
+    debug!("Current shader: {}", screen_shader);
+    if let ScreenShader::External(path) = &screen_shader {
+        if config_data.respect_external_shader {
+            if config_data.screen_shader != screen_shader {
+                info!(
+                    "Shader {} was set by something else, leaving it alone",
+                    path
+                );
+            }
+            config_data.screen_shader = screen_shader;
+            return;
+        }
+        info!("Replacing shader {} set by something else", path);
+    }
+    config_data.screen_shader = screen_shader;
+
+    if config_data.screen_shader.is_applied(hash.as_deref()) {
+        return;
+    }
+
+    // Failures leave the shader unknown, so that it's checked again next time. Files of
+    // replaced shaders are removed once they're no longer in use.
+    config_data.screen_shader = match hash {
+        None => match shader::remove(compositor, config_data.baseline.as_deref()) {
+            Ok(()) => {
+                config_data.store.retain(None);
+                ScreenShader::Applied(None)
+            }
+            Err(error) => {
+                error!("Failed to remove shader: {:?}", error);
+                ScreenShader::Unknown
+            }
+        },
+        Some(hash) => match shader::apply(compositor, &config_data.store, &shader_to_apply) {
+            Ok(applied) => {
+                config_data.store.retain(Some(&applied.path));
+                ScreenShader::Applied(Some(applied))
+            }
+            Err(error) => {
+                error!("Failed to apply shader {}: {:?}", hash, error);
+                ScreenShader::Unknown
+            }
+        },
+    };
+}
+
+pub fn apply_shaders_for_active_window(compositor: &dyn Compositor, config_data: &mut ConfigData) {
+    let context = compositor.active_window().unwrap_or_else(|error| {
+        error!("Failed to get active window: {:?}", error);
+        WindowContext::default()
+    });
+
+    apply_shaders(compositor, config_data, &context);
+}
+
+pub fn refresh_monitors(compositor: &dyn Compositor, config_data: &mut ConfigData) {
+    match compositor.monitors() {
+        Ok(monitors) => config_data.monitors = monitors,
+        Err(error) => error!("Failed to get monitors: {:?}", error),
+    }
+}
+
+/// Takes the current screen shader as the baseline that shows when no effects apply.
+pub fn capture_baseline(compositor: &dyn Compositor, config_data: &mut ConfigData) {
+    match compositor.screen_shader() {
+        Ok(Some(path)) => match config_data.store.hash(&path) {
+            // Left behind by a run that didn't exit cleanly, which recorded the baseline
+            Some(hash) => {
+                info!("Found shader {} from a previous run", hash);
+                config_data.baseline = config_data.store.baseline();
+                config_data.screen_shader =
+                    ScreenShader::Applied(Some(AppliedShader { hash, path }));
+            }
+            None => {
+                info!("Using screen shader {} as the baseline", path);
+                config_data.store.set_baseline(Some(&path));
+                config_data.baseline = Some(path);
+                config_data.screen_shader = ScreenShader::Applied(None);
+            }
+        },
+        Ok(None) => {
+            config_data.store.set_baseline(None);
+            config_data.baseline = None;
+            config_data.screen_shader = ScreenShader::Applied(None);
+        }
+        Err(error) => {
+            error!("Failed to get the baseline shader: {:?}", error);
+            config_data.screen_shader = ScreenShader::Unknown;
+        }
+    }
+}
+
+/// Re-evaluates shaders after a compositor event.
+pub fn handle_event(compositor: &dyn Compositor, config_data: &mut ConfigData, event: Event) {
+    match event {
+        // The events lack the window state, so the full window is queried
+        Event::ActiveWindowChanged | Event::WorkspaceChanged | Event::WindowStateChanged => {}
+        // Reloading resets the shader to the one in hyprland.conf
+        Event::ConfigReloaded => capture_baseline(compositor, config_data),
+        // Re-generate the shader for the new monitor layout when monitors are hot-plugged
+        Event::MonitorAdded(name) => {
+            info!("Monitor added: {}", name);
+            refresh_monitors(compositor, config_data);
+        }
+        Event::MonitorRemoved(name) => {
+            info!("Monitor removed: {}", name);
+            refresh_monitors(compositor, config_data);
+        }
+    }
+
+    apply_shaders_for_active_window(compositor, config_data);
+}
@@ -0,0 +1,293 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use log::{error, info};
+
+use hyprlux::utils;
+
+const SOCKET_NAME: &str = "hyprlux.sock";
+const ERROR_PREFIX: &str = "error: ";
+// Clients send their request right away, so a silent one must not hold up the others
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, PartialEq)]
+pub enum PauseDuration {
+    For(Duration),
+    // Until the night light ends, i.e. sunrise in location mode
+    UntilSunrise,
+}
+
+/// Commands accepted by the daemon, both as `hyprlux ctl` arguments and over the socket.
+#[derive(Debug, PartialEq)]
+pub enum Request {
+    Status,
+    ToggleNightLight,
+    SetTemperature(i32),
+    Pause(PauseDuration),
+    Resume,
+    Reload,
+    ApplyVibrance(Option<i32>),
+}
+
+pub const USAGE: &str = "Usage: hyprlux ctl <command>
+
+Commands:
+    status                    Show the current state
+    toggle night-light        Enable or disable night light
+    set temperature <kelvin>  Set the night light temperature
+    pause <duration|sunrise>  Disable all shaders for a while, e.g. 30m or 1h30m
+    resume                    Resume a pause early
+    reload                    Reload the config file
+    apply vibrance <strength|off>
+                              Force vibrance regardless of the active window";
+
+impl Request {
+    pub fn parse(args: &[&str]) -> Result<Self, String> {
+        match args {
+            ["status"] => Ok(Request::Status),
+            ["toggle", "night-light"] => Ok(Request::ToggleNightLight),
+            ["set", "temperature", value] => value
+                .parse()
+                .map(Request::SetTemperature)
+                .map_err(|_| format!("Invalid temperature: {}", value)),
+            ["pause", "sunrise"] => Ok(Request::Pause(PauseDuration::UntilSunrise)),
+            ["pause", duration] => utils::parse_duration(duration)
+                .map(|duration| Request::Pause(PauseDuration::For(duration)))
+                .ok_or(format!("Invalid duration: {}", duration)),
+            ["resume"] => Ok(Request::Resume),
+            ["reload"] => Ok(Request::Reload),
+            ["apply", "vibrance", "off"] => Ok(Request::ApplyVibrance(None)),
+            ["apply", "vibrance", value] => value
+                .parse()
+                .map(|strength| Request::ApplyVibrance(Some(strength)))
+                .map_err(|_| format!("Invalid vibrance strength: {}", value)),
+            _ => Err(format!("Unknown command: {}", args.join(" "))),
+        }
+    }
+}
+
+impl FromStr for Request {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Request::parse(&s.split_whitespace().collect::<Vec<&str>>())
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Request::Status => write!(f, "status"),
+            Request::ToggleNightLight => write!(f, "toggle night-light"),
+            Request::SetTemperature(value) => write!(f, "set temperature {}", value),
+            Request::Pause(PauseDuration::For(duration)) => {
+                write!(f, "pause {}s", duration.as_secs())
+            }
+            Request::Pause(PauseDuration::UntilSunrise) => write!(f, "pause sunrise"),
+            Request::Resume => write!(f, "resume"),
+            Request::Reload => write!(f, "reload"),
+            Request::ApplyVibrance(None) => write!(f, "apply vibrance off"),
+            Request::ApplyVibrance(Some(strength)) => write!(f, "apply vibrance {}", strength),
+        }
+    }
+}
+
+pub fn socket_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(xdg::BaseDirectories::new()?
+        .get_runtime_directory()?
+        .join(SOCKET_NAME))
+}
+
+/// Listens for requests on `path` and answers each with the result of `handler`.
+/// Fails with `AddrInUse` if another daemon is already listening there.
+pub fn serve<F>(path: &Path, handler: F) -> io::Result<thread::JoinHandle<()>>
+where
+    F: Fn(Request) -> Result<String, String> + Send + 'static,
+{
+    if UnixStream::connect(path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("another instance is listening on {}", path.display()),
+        ));
+    }
+
+    // Remove a stale socket left behind by a previous instance
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+    info!("Listening for commands on {}", path.display());
+
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(error) = handle(stream, &handler) {
+                        error!("Failed to handle command: {:?}", error);
+                    }
+                }
+                Err(error) => error!("Socket error: {:?}", error),
+            }
+        }
+    }))
+}
+
+fn handle<F>(mut stream: UnixStream, handler: &F) -> io::Result<()>
+where
+    F: Fn(Request) -> Result<String, String>,
+{
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut line = String::new();
+    // Nothing to answer if the client hung up without a request, e.g. a liveness check
+    if BufReader::new(&stream).read_line(&mut line)? == 0 {
+        return Ok(());
+    }
+
+    let response = line.parse().and_then(|request: Request| {
+        info!("Received command: {}", request);
+        handler(request)
+    });
+
+    match response {
+        Ok(message) => writeln!(stream, "{}", message),
+        Err(message) => writeln!(stream, "{}{}", ERROR_PREFIX, message),
+    }
+}
+
+/// Sends a request to the daemon listening on `path` and returns its response.
+pub fn send(path: &Path, request: &Request) -> Result<String, Box<dyn std::error::Error>> {
+    let mut stream = UnixStream::connect(path)?;
+    writeln!(stream, "{}", request)?;
+
+    let mut response = String::new();
+    for line in BufReader::new(stream).lines() {
+        response.push_str(&line?);
+        response.push('\n');
+    }
+
+    match response.strip_prefix(ERROR_PREFIX) {
+        Some(message) => Err(message.trim_end().into()),
+        None => Ok(response.trim_end().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let requests = [
+            ("status", Ok(Request::Status)),
+            ("toggle night-light", Ok(Request::ToggleNightLight)),
+            ("set temperature 4000", Ok(Request::SetTemperature(4000))),
+            (
+                "set temperature warm",
+                Err("Invalid temperature: warm".to_string()),
+            ),
+            (
+                "pause 30m",
+                Ok(Request::Pause(PauseDuration::For(Duration::from_secs(
+                    30 * 60,
+                )))),
+            ),
+            (
+                "pause sunrise",
+                Ok(Request::Pause(PauseDuration::UntilSunrise)),
+            ),
+            ("pause later", Err("Invalid duration: later".to_string())),
+            ("resume", Ok(Request::Resume)),
+            ("reload", Ok(Request::Reload)),
+            ("apply vibrance 150", Ok(Request::ApplyVibrance(Some(150)))),
+            ("apply vibrance off", Ok(Request::ApplyVibrance(None))),
+            (
+                "toggle vibrance",
+                Err("Unknown command: toggle vibrance".to_string()),
+            ),
+        ];
+        for (line, expected) in requests {
+            assert_eq!(line.parse::<Request>(), expected, "{}", line)
+        }
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let requests = [
+            Request::Status,
+            Request::ToggleNightLight,
+            Request::SetTemperature(4000),
+            Request::Pause(PauseDuration::For(Duration::from_secs(90))),
+            Request::Pause(PauseDuration::UntilSunrise),
+            Request::Resume,
+            Request::Reload,
+            Request::ApplyVibrance(Some(150)),
+            Request::ApplyVibrance(None),
+        ];
+        for request in requests {
+            assert_eq!(request.to_string().parse::<Request>(), Ok(request))
+        }
+    }
+
+    #[test]
+    fn test_serve_and_send() {
+        let path = std::env::temp_dir().join(format!("hyprlux-test-{}.sock", std::process::id()));
+
+        serve(&path, |request| match request {
+            Request::Status => Ok("night light: on".to_string()),
+            _ => Err("unsupported".to_string()),
+        })
+        .unwrap();
+
+        assert_eq!(
+            send(&path, &Request::Status).unwrap(),
+            "night light: on".to_string()
+        );
+        assert_eq!(
+            send(&path, &Request::Reload).unwrap_err().to_string(),
+            "unsupported".to_string()
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_silent_client() {
+        let path =
+            std::env::temp_dir().join(format!("hyprlux-test-silent-{}.sock", std::process::id()));
+
+        serve(&path, |_| Ok("ok".to_string())).unwrap();
+
+        // A client that never sends a request doesn't block the next one
+        let _silent = UnixStream::connect(&path).unwrap();
+        assert_eq!(send(&path, &Request::Status).unwrap(), "ok".to_string());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_serve_in_use() {
+        let path =
+            std::env::temp_dir().join(format!("hyprlux-test-in-use-{}.sock", std::process::id()));
+
+        serve(&path, |_| Ok("first".to_string())).unwrap();
+        let error = serve(&path, |_| Ok("second".to_string())).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert_eq!(send(&path, &Request::Status).unwrap(), "first".to_string());
+
+        // A socket nobody listens on is stale and gets replaced
+        fs::remove_file(&path).unwrap();
+        drop(UnixListener::bind(&path).unwrap());
+        serve(&path, |_| Ok("third".to_string())).unwrap();
+        assert_eq!(send(&path, &Request::Status).unwrap(), "third".to_string());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
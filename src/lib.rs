@@ -0,0 +1,6 @@
+//! Shader composition and window rule matching for the hyprlux daemon.
+
+pub mod compositor;
+pub mod daemon;
+pub mod shaders;
+pub mod utils;
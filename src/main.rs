@@ -1,185 +1,643 @@
 mod config;
-mod shaders;
-mod utils;
+mod ipc;
+mod pause;
+mod scheduler;
+mod signals;
+mod watcher;
 
-use hyprland::event_listener::EventListener;
+use chrono::{DateTime, Local, TimeDelta};
+use config::{
+    ConfigError, MatcherConfig, NightLightConfig, ShaderSourceConfig, WindowConditionsConfig,
+};
+use hyprland::ctl::notify::Icon;
+use hyprland::ctl::{self, Color};
+use hyprlux::compositor::{self, Compositor};
+use hyprlux::daemon::{self, ConfigData, TIME_FMT};
+use hyprlux::shaders;
+use ipc::{PauseDuration, Request};
 use log::{debug, error, info};
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use shaders::night_light::NightLightShader;
-use shaders::shader::{self, Shader};
-use std::sync::{mpsc, Arc, Mutex};
-use std::thread;
-use std::time::{Duration, Instant};
-
-fn main() -> hyprland::Result<()> {
+use shaders::adjustment::Adjustment;
+use shaders::custom::{self, CustomShader};
+use shaders::monitor;
+use shaders::rule::{self, Effect, Rule};
+use shaders::shader::{self, ScreenShader};
+use shaders::store::ShaderStore;
+use shaders::window_rule::{WindowConditions, WindowRule};
+use signals::Signal;
+use std::io;
+use std::path::Path;
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::time::Duration;
+use std::{env, fs, process};
+
+/// Longest pause accepted from `hyprlux ctl pause`.
+const MAX_PAUSE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::builder()
         .filter_level(log::LevelFilter::Info)
         .init();
 
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "ctl" {
+        process::exit(ctl(&args[2..]));
+    }
+    if args.len() > 1 && args[1] == "config" {
+        process::exit(config_command(&args[2..]));
+    }
+
     let config_path = config::path();
-    let config_data = Arc::new(Mutex::new(load_config_and_shaders(&config_path)));
+    let mut config_data = match load_config_and_shaders(&config_path) {
+        Ok(config_data) => config_data,
+        Err(error) => {
+            error!("Invalid config, no shaders will be applied: {}", error);
+            ConfigData::default()
+        }
+    };
 
-    // Channel for notifying when the config file changes
-    let (tx, rx) = mpsc::channel();
+    let compositor: Arc<dyn Compositor> = Arc::new(compositor::hyprland::new());
+    daemon::refresh_monitors(compositor.as_ref(), &mut config_data);
+    daemon::capture_baseline(compositor.as_ref(), &mut config_data);
 
-    let mut watcher = RecommendedWatcher::new(
-        tx,
-        notify::Config::default()
-            .with_poll_interval(Duration::from_secs(2))
-            .with_compare_contents(false),
-    )
-    .unwrap();
+    // Resume a pause that was active when the daemon stopped
+    match pause::state_path() {
+        Ok(path) => config_data.paused_until = pause::load(&path),
+        Err(error) => error!("Failed to get pause state path: {:?}", error),
+    }
 
-    watcher
-        .watch(config_path.as_ref(), RecursiveMode::NonRecursive)
-        .unwrap();
+    let config_data = Arc::new(Mutex::new(config_data));
 
-    let config_data_clone = Arc::clone(&config_data);
+    // Channel for waking up the scheduler when the schedule might have changed
+    let (wake_tx, wake_rx) = mpsc::channel();
 
-    if config_data_clone.lock().unwrap().hot_reload {
-        // Spawn a thread to watch for config changes and reload shaders
-        let debounce_delay = Duration::from_millis(2000);
-        let mut last_event_time = Instant::now();
-        thread::spawn(move || loop {
-            match rx.recv() {
-                Ok(_) => {
-                    let now = Instant::now();
-                    if now.duration_since(last_event_time) > debounce_delay {
-                        info!("Config file changed. Reloading...");
-
-                        let new_config = load_config_and_shaders(&config_path);
-
-                        let mut config_data = config_data_clone.lock().unwrap();
-
-                        // Only load config if it's not the same and it contains data
-                        if new_config != *config_data
-                            && new_config.night_light_shader.is_some()
-                            && !new_config.vibrance_shaders.is_empty()
-                        {
-                            *config_data = load_config_and_shaders(&config_path);
-                            last_event_time = now;
-                        }
-                    } else {
-                        info!("Ignoring duplicate event within debounce period");
-                    }
+    // Serve commands from `hyprlux ctl`
+    match ipc::socket_path() {
+        Ok(socket_path) => {
+            let compositor_clone = Arc::clone(&compositor);
+            let config_data_clone = Arc::clone(&config_data);
+            let config_path_clone = config_path.clone();
+            let wake_tx_clone = wake_tx.clone();
+            if let Err(error) = ipc::serve(&socket_path, move |request| {
+                dispatch(
+                    compositor_clone.as_ref(),
+                    request,
+                    &config_path_clone,
+                    &config_data_clone,
+                    &wake_tx_clone,
+                )
+            }) {
+                if error.kind() == io::ErrorKind::AddrInUse {
+                    return Err(error.into());
                 }
-                Err(error) => error!("Watch error: {:?}", error),
+                error!("Failed to listen for commands: {:?}", error);
             }
-        });
+        }
+        Err(error) => error!("Failed to get socket path: {:?}", error),
     }
 
-    // Setup the event listener
-    let mut event_listener = EventListener::new();
-
-    // Event handler logic
-    event_listener.add_active_window_changed_handler(move |data| {
-        let applied_shader = shader::get().unwrap_or("null".to_string());
-        debug!("Current shader: {}", applied_shader);
-        let mut shader_to_apply: Option<Box<dyn shader::Shader>> = None;
-
-        let (window_class, window_title) = match data {
-            Some(d) => (Some(d.class.to_string()), Some(d.title.to_string())),
-            None => {
-                debug!("No active window, evaluating shaders without window context");
-                (None, None)
+    // Toggle night light on SIGUSR1, reload on SIGUSR2 and clean up on SIGTERM/SIGINT
+    let compositor_clone = Arc::clone(&compositor);
+    let config_data_clone = Arc::clone(&config_data);
+    let config_path_clone = config_path.clone();
+    let wake_tx_clone = wake_tx.clone();
+    if let Err(error) = signals::spawn(move |signal| {
+        let request = match signal {
+            Signal::ToggleNightLight => Request::ToggleNightLight,
+            Signal::Reload => Request::Reload,
+            Signal::Terminate => {
+                // Keep other threads from applying shaders while shutting down
+                let config_data = config_data_clone
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                shutdown(compositor_clone.as_ref(), &config_data);
             }
         };
 
-        // Access the current config and shaders
-        let config_data = config_data.lock().unwrap();
-
-        // Should apply night light shader?
-        if config_data.night_light_shader.is_some() {
-            let shader = config_data.night_light_shader.clone().unwrap();
-            if shader.should_apply(window_class.clone(), window_title.clone()) {
-                shader_to_apply = Some(Box::new(shader));
-            }
+        match dispatch(
+            compositor_clone.as_ref(),
+            request,
+            &config_path_clone,
+            &config_data_clone,
+            &wake_tx_clone,
+        ) {
+            Ok(message) => info!("{}", message),
+            Err(message) => error!("{}", message),
         }
+    }) {
+        error!("Failed to listen for signals: {:?}", error);
+    }
 
-        // Should apply vibrance shader?
-        for vibrance_shader in &config_data.vibrance_shaders {
-            if vibrance_shader.should_apply(window_class.clone(), window_title.clone()) {
-                shader_to_apply = Some(Box::new(vibrance_shader.clone()));
-                break;
-            }
-        }
+    // Watch for config changes and reload shaders. The watcher always runs so that
+    // `hot_reload` can be switched on in the file itself.
+    let compositor_clone = Arc::clone(&compositor);
+    let config_data_clone = Arc::clone(&config_data);
+    let config_path_clone = config_path.clone();
+    let wake_tx_clone = wake_tx.clone();
+    let _watcher = watcher::spawn(Path::new(&config_path), move || {
+        let mut config_data = config_data_clone
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
 
-        // Remove current shader if none should apply
-        if shader_to_apply.is_none() && applied_shader != *"null" {
-            shader::remove().unwrap();
-            return;
-        } else if shader_to_apply.is_none() {
-            return;
+        match load_config_and_shaders(&config_path_clone) {
+            Ok(new_config) if config_data.hot_reload || new_config.hot_reload => {
+                info!("Config file changed. Reloading...");
+                if daemon::replace_config(&mut config_data, new_config) {
+                    daemon::apply_shaders_for_active_window(
+                        compositor_clone.as_ref(),
+                        &mut config_data,
+                    );
+                    let _ = wake_tx_clone.send(());
+                }
+            }
+            Ok(_) => debug!("Config file changed, but hot reload is disabled"),
+            Err(error) if config_data.hot_reload => {
+                error!("Invalid config, keeping the previous one: {}", error);
+                if config_data.notify_errors {
+                    notify_config_error(&error);
+                }
+            }
+            Err(error) => debug!("Config file changed, but is invalid: {}", error),
         }
+    })
+    .map_err(|error| error!("Failed to watch config file: {:?}", error));
 
-        let shader_to_apply = shader_to_apply.unwrap();
-        // Apply shader if needed
-        if shader_to_apply.hash() != applied_shader {
-            shader::apply(shader_to_apply.as_ref()).unwrap();
-        }
-    });
+    // Re-evaluate shaders whenever a schedule boundary (e.g. night light start) is reached
+    let next_change_config_data = Arc::clone(&config_data);
+    let on_change_config_data = Arc::clone(&config_data);
+    let compositor_clone = Arc::clone(&compositor);
+    scheduler::spawn(
+        move || {
+            next_change_config_data
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .next_change()
+        },
+        wake_rx,
+        move || {
+            info!("Schedule boundary reached. Re-evaluating shaders...");
+            daemon::apply_shaders_for_active_window(
+                compositor_clone.as_ref(),
+                &mut on_change_config_data
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner),
+            );
+        },
+    );
 
-    event_listener.start_listener()?;
+    let compositor_clone = Arc::clone(&compositor);
+    compositor.listen(Box::new(move |event| {
+        daemon::handle_event(
+            compositor_clone.as_ref(),
+            &mut config_data.lock().unwrap_or_else(PoisonError::into_inner),
+            event,
+        )
+    }))?;
 
     Ok(())
 }
 
-fn load_config_and_shaders(config_path: &str) -> ConfigData {
-    let cfg = config::load(config_path.to_string());
+fn load_config_and_shaders(config_path: &str) -> Result<ConfigData, ConfigError> {
+    let cfg = config::load(config_path.to_string())?;
     if cfg.is_none() {
-        return ConfigData {
-            night_light_shader: None,
-            vibrance_shaders: [].to_vec(),
-            hot_reload: false,
-        };
+        return Ok(ConfigData::default());
     }
 
     let cfg = cfg.unwrap();
     info!("Config loaded: {:?}", cfg);
 
-    let night_light_shader: Option<NightLightShader>;
-    if cfg.night_light.latitude.is_some() && cfg.night_light.longitude.is_some() {
-        night_light_shader = Some(shaders::night_light::new_from_location(
-            cfg.night_light.enabled,
-            cfg.night_light.latitude.unwrap(),
-            cfg.night_light.longitude.unwrap(),
-            cfg.night_light.temperature,
-            None,
-        ));
-    } else if cfg.night_light.start_time.is_some() && cfg.night_light.end_time.is_some() {
-        night_light_shader = Some(shaders::night_light::new(
-            cfg.night_light.enabled,
-            cfg.night_light.start_time.unwrap(),
-            cfg.night_light.end_time.unwrap(),
-            cfg.night_light.temperature,
-            None,
-        ));
-    } else {
-        night_light_shader = None;
+    let night_light_rule: Option<Rule> = match cfg.night_light {
+        NightLightConfig {
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            ..
+        } => Some(
+            shaders::night_light::new_from_location(
+                cfg.night_light.enabled,
+                latitude,
+                longitude,
+                cfg.night_light.temperature,
+                None,
+            )
+            .map_err(field_error(config_path, "night_light.latitude".to_string()))?,
+        ),
+        NightLightConfig {
+            start_time: Some(ref start_time),
+            end_time: Some(ref end_time),
+            ..
+        } => Some(
+            shaders::night_light::new(
+                cfg.night_light.enabled,
+                start_time.clone(),
+                end_time.clone(),
+                cfg.night_light.temperature,
+                None,
+            )
+            .map_err(field_error(
+                config_path,
+                "night_light.start_time".to_string(),
+            ))?,
+        ),
+        _ => None,
     }
+    .map(|shader| -> Result<Rule, regex::Error> {
+        let shader = shader
+            .with_transition(
+                cfg.night_light
+                    .transition_duration
+                    .unwrap_or(0)
+                    .checked_mul(60)
+                    .map_or(shaders::night_light::MAX_TRANSITION, Duration::from_secs),
+                cfg.night_light.transition_step.unwrap_or(100),
+            )
+            .with_exclusions(
+                cfg.night_light
+                    .exclude
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|rule_cfg| {
+                        window_rule(
+                            rule_cfg.window_class,
+                            rule_cfg.window_title,
+                            rule_cfg.workspace,
+                            rule_cfg.conditions,
+                        )
+                    })
+                    .collect::<Result<Vec<WindowRule>, regex::Error>>()?,
+            );
+        let monitor = cfg
+            .night_light
+            .monitor
+            .clone()
+            .map(monitor::new)
+            .transpose()?;
+
+        Ok(rule::new(
+            shaders::window_rule::any(),
+            vec![Effect::Temperature(shader)],
+        )
+        .with_name(daemon::NIGHT_LIGHT_RULE)
+        .with_monitor(monitor))
+    })
+    .transpose()
+    .map_err(field_error(config_path, "night_light".to_string()))?;
 
-    let vibrance_shaders: Vec<shaders::vibrance::VibranceShader> = cfg
+    let vibrance_rules = cfg
         .vibrance_configs
         .into_iter()
-        .map(|vibrance_cfg| {
-            shaders::vibrance::new(
+        .enumerate()
+        .map(|(i, vibrance_cfg)| {
+            let field = format!("vibrance_configs[{}]", i);
+            let window = window_rule(
                 vibrance_cfg.window_class,
                 vibrance_cfg.window_title,
-                vibrance_cfg.strength,
+                vibrance_cfg.workspace,
+                vibrance_cfg.conditions,
+            )
+            .map_err(field_error(config_path, field.clone()))?;
+            let monitor = vibrance_cfg
+                .monitor
+                .map(monitor::new)
+                .transpose()
+                .map_err(field_error(config_path, field))?;
+
+            Ok(rule::new(
+                window,
+                vec![Effect::Vibrance(shaders::vibrance::new(
+                    vibrance_cfg.strength,
+                ))],
+            )
+            .with_monitor(monitor))
+        })
+        .collect::<Result<Vec<Rule>, ConfigError>>()?;
+
+    let custom_rules = cfg
+        .custom_shaders
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, custom_cfg)| {
+            let shader = custom_shader(custom_cfg.shader)?;
+            let field = format!("custom_shaders[{}]", i);
+            let window = match window_rule(
+                custom_cfg.window_class,
+                custom_cfg.window_title,
+                custom_cfg.workspace,
+                custom_cfg.conditions,
+            ) {
+                Ok(window) => window,
+                Err(error) => return Some(Err(field_error(config_path, field)(error))),
+            };
+
+            Some(
+                rule::new(window, vec![Effect::Custom(shader)])
+                    .with_schedule(custom_cfg.start_time.zip(custom_cfg.end_time), None)
+                    .map_err(field_error(config_path, field)),
             )
         })
-        .collect();
+        .collect::<Result<Vec<Rule>, ConfigError>>()?;
 
-    ConfigData {
-        night_light_shader,
-        vibrance_shaders,
+    let rules = cfg
+        .rules
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .map(|(i, rule_cfg)| {
+            let field = format!("rules[{}]", i);
+            let window = window_rule(
+                rule_cfg.window_class,
+                rule_cfg.window_title,
+                rule_cfg.workspace,
+                rule_cfg.conditions,
+            )
+            .map_err(field_error(config_path, field.clone()))?;
+            let monitor = rule_cfg
+                .monitor
+                .map(monitor::new)
+                .transpose()
+                .map_err(field_error(config_path, field.clone()))?;
+
+            let effects = rule_cfg
+                .temperature
+                .map(|temperature| {
+                    Effect::Temperature(shaders::night_light::new_always(temperature, None))
+                })
+                .into_iter()
+                .chain(
+                    rule_cfg
+                        .vibrance
+                        .map(|strength| Effect::Vibrance(shaders::vibrance::new(strength))),
+                )
+                .chain(
+                    [
+                        (Adjustment::Brightness, rule_cfg.brightness),
+                        (Adjustment::Gamma, rule_cfg.gamma),
+                        (Adjustment::Contrast, rule_cfg.contrast),
+                    ]
+                    .into_iter()
+                    .filter_map(|(adjustment, value)| {
+                        Some(Effect::Adjustment(shaders::adjustment::new(
+                            adjustment, value?,
+                        )))
+                    }),
+                )
+                .chain(
+                    rule_cfg
+                        .custom_shader
+                        .and_then(custom_shader)
+                        .map(Effect::Custom),
+                )
+                .collect();
+
+            Ok(rule::new(window, effects)
+                .with_priority(rule_cfg.priority)
+                .with_schedule(rule_cfg.start_time.zip(rule_cfg.end_time), None)
+                .map_err(field_error(config_path, field))?
+                .with_monitor(monitor))
+        })
+        .collect::<Result<Vec<Rule>, ConfigError>>()?;
+
+    let rules = rule::list(
+        night_light_rule
+            .into_iter()
+            .chain(vibrance_rules)
+            .chain(custom_rules)
+            .chain(rules)
+            .collect(),
+    )
+    .map_err(field_error(config_path, "rules".to_string()))?;
+
+    Ok(ConfigData {
+        rules,
         hot_reload: cfg.hot_reload.unwrap_or(false),
+        notify_errors: cfg.notify_errors.unwrap_or(false),
+        respect_external_shader: cfg.respect_external_shader.unwrap_or(false),
+        compose_baseline: cfg.compose_baseline.unwrap_or(false),
+        paused_until: None,
+        vibrance_override: None,
+        monitors: Vec::new(),
+        screen_shader: ScreenShader::Unknown,
+        baseline: None,
+        store: ShaderStore::default(),
+    })
+}
+
+fn custom_shader(shader_cfg: ShaderSourceConfig) -> Option<CustomShader> {
+    let source = match (shader_cfg.path, shader_cfg.source) {
+        (Some(path), _) => custom::Source::File(path.into()),
+        (None, Some(source)) => custom::Source::Inline(source),
+        (None, None) => {
+            error!("Custom shader {} has no path or source", shader_cfg.name);
+            return None;
+        }
+    };
+
+    Some(custom::new(
+        shader_cfg.name,
+        source,
+        shader_cfg.vars.unwrap_or_default(),
+    ))
+}
+
+fn window_rule(
+    window_class: Option<MatcherConfig>,
+    window_title: Option<MatcherConfig>,
+    workspace: Option<MatcherConfig>,
+    conditions: WindowConditionsConfig,
+) -> Result<WindowRule, regex::Error> {
+    Ok(shaders::window_rule::new(
+        config::matcher(&window_class)?,
+        config::matcher(&window_title)?,
+    )
+    .with_combinator(conditions.combinator.map(Into::into).unwrap_or_default())
+    .with_workspace(config::matcher(&workspace)?)
+    .with_conditions(WindowConditions {
+        initial_class: config::matcher(&conditions.initial_class)?,
+        initial_title: config::matcher(&conditions.initial_title)?,
+        pid: conditions.pid,
+        fullscreen: conditions.fullscreen,
+        floating: conditions.floating,
+        xwayland: conditions.xwayland,
+        pinned: conditions.pinned,
+    }))
+}
+
+/// Error for a field that passed validation but still failed to build, e.g. a regex
+/// exceeding the size limit.
+fn field_error<E: ToString>(config_path: &str, field: String) -> impl FnOnce(E) -> ConfigError {
+    let path = config_path.to_string();
+    move |error| ConfigError::Invalid {
+        path,
+        line: None,
+        field,
+        message: error.to_string(),
+    }
+}
+
+fn notify_config_error(error: &ConfigError) {
+    if let Err(error) = ctl::notify::call(
+        Icon::Error,
+        Duration::from_secs(10),
+        Color::new(255, 85, 85, 255),
+        format!("hyprlux: {}", error),
+    ) {
+        error!("Failed to send notification: {:?}", error);
+    }
+}
+
+/// Handles a request, then re-applies shaders and reschedules for the changed state.
+fn dispatch(
+    compositor: &dyn Compositor,
+    request: Request,
+    config_path: &str,
+    config_data: &Mutex<ConfigData>,
+    wake_tx: &mpsc::Sender<()>,
+) -> Result<String, String> {
+    let mut config_data = config_data.lock().unwrap_or_else(PoisonError::into_inner);
+    let response = handle_request(request, config_path, &mut config_data);
+
+    daemon::apply_shaders_for_active_window(compositor, &mut config_data);
+    let _ = wake_tx.send(());
+
+    response
+}
+
+/// Restores the screen and exits.
+fn shutdown(compositor: &dyn Compositor, config_data: &ConfigData) -> ! {
+    info!("Shutting down...");
+
+    // Shaders set by something else are left alone
+    if let ScreenShader::Applied(Some(_)) = config_data.screen_shader {
+        if let Err(error) = shader::remove(compositor, config_data.baseline.as_deref()) {
+            error!("Failed to remove shader: {:?}", error);
+        }
+    }
+    config_data.store.set_baseline(None);
+    config_data.store.retain(None);
+    if let Ok(socket_path) = ipc::socket_path() {
+        let _ = fs::remove_file(socket_path);
+    }
+
+    process::exit(0);
+}
+
+fn handle_request(
+    request: Request,
+    config_path: &str,
+    config_data: &mut ConfigData,
+) -> Result<String, String> {
+    match request {
+        Request::Status => Ok(config_data.status()),
+        Request::ToggleNightLight => match config_data.night_light() {
+            Some(shader) => {
+                let enabled = !shader.is_enabled();
+                config_data
+                    .night_lights_mut()
+                    .for_each(|shader| shader.set_enabled(enabled));
+                Ok(format!(
+                    "Night light {}",
+                    if enabled { "enabled" } else { "disabled" }
+                ))
+            }
+            None => Err("Night light is not configured".to_string()),
+        },
+        Request::SetTemperature(temperature)
+            if !(config::MIN_TEMPERATURE..=config::MAX_TEMPERATURE).contains(&temperature) =>
+        {
+            Err(format!(
+                "Temperature must be between {} and {}",
+                config::MIN_TEMPERATURE,
+                config::MAX_TEMPERATURE
+            ))
+        }
+        Request::SetTemperature(temperature) => match config_data.night_light() {
+            Some(_) => {
+                config_data
+                    .night_lights_mut()
+                    .for_each(|shader| shader.set_temperature(temperature));
+                Ok(format!("Temperature set to {}K", temperature))
+            }
+            None => Err("Night light is not configured".to_string()),
+        },
+        Request::Pause(duration) => {
+            let duration = match duration {
+                PauseDuration::For(duration) => duration,
+                PauseDuration::UntilSunrise => match config_data.night_light() {
+                    Some(shader) => shader.until_end(),
+                    None => return Err("Night light is not configured".to_string()),
+                },
+            };
+
+            let until = TimeDelta::from_std(duration)
+                .ok()
+                .filter(|_| duration <= MAX_PAUSE)
+                .and_then(|delta| Local::now().checked_add_signed(delta))
+                .ok_or("duration out of range".to_string())?;
+            config_data.paused_until = Some(until);
+            save_pause(Some(until));
+            Ok(format!("Paused until {}", until.format(TIME_FMT)))
+        }
+        Request::Resume => {
+            config_data.paused_until = None;
+            save_pause(None);
+            Ok("Resumed".to_string())
+        }
+        Request::Reload => {
+            let new_config = load_config_and_shaders(config_path).map_err(|e| e.to_string())?;
+            match daemon::replace_config(config_data, new_config) {
+                true => Ok("Config reloaded".to_string()),
+                false => Ok("Config unchanged".to_string()),
+            }
+        }
+        Request::ApplyVibrance(strength) => {
+            config_data.vibrance_override = strength.map(shaders::vibrance::new);
+            match strength {
+                Some(strength) => Ok(format!("Vibrance forced to {}", strength)),
+                None => Ok("Vibrance follows window rules".to_string()),
+            }
+        }
     }
 }
 
-#[derive(PartialEq)]
-struct ConfigData {
-    night_light_shader: Option<shaders::night_light::NightLightShader>,
-    vibrance_shaders: Vec<shaders::vibrance::VibranceShader>,
-    hot_reload: bool,
+fn save_pause(until: Option<DateTime<Local>>) {
+    match pause::state_path() {
+        Ok(path) => pause::save(&path, until),
+        Err(error) => error!("Failed to get pause state path: {:?}", error),
+    }
+}
+
+/// Runs `hyprlux config` and returns the process exit code.
+fn config_command(args: &[String]) -> i32 {
+    match args.iter().map(String::as_str).collect::<Vec<&str>>()[..] {
+        ["dump-defaults"] => {
+            print!("{}", config::dump_defaults());
+            0
+        }
+        _ => {
+            eprintln!("{}", config::USAGE);
+            2
+        }
+    }
+}
+
+/// Runs `hyprlux ctl` and returns the process exit code.
+fn ctl(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let request = match Request::parse(&args) {
+        Ok(request) => request,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, ipc::USAGE);
+            return 2;
+        }
+    };
+
+    match ipc::socket_path().and_then(|path| ipc::send(&path, &request)) {
+        Ok(response) => {
+            println!("{}", response);
+            0
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
 }
@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+use log::{error, info};
+
+const STATE_FILE: &str = "paused_until";
+
+pub fn state_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(xdg::BaseDirectories::with_prefix("hyprlux")?.place_state_file(STATE_FILE)?)
+}
+
+/// Returns the end of a pause persisted by a previous instance, if it hasn't expired yet.
+pub fn load(path: &Path) -> Option<DateTime<Local>> {
+    let contents = fs::read_to_string(path).ok()?;
+
+    match DateTime::parse_from_rfc3339(contents.trim()) {
+        Ok(until) if until > Local::now() => {
+            info!("Restoring pause until {}", until);
+            Some(until.with_timezone(&Local))
+        }
+        Ok(_) => {
+            save(path, None);
+            None
+        }
+        Err(error) => {
+            error!("Invalid pause state in {}: {:?}", path.display(), error);
+            None
+        }
+    }
+}
+
+/// Persists the end of the pause, or clears it when `until` is `None`.
+pub fn save(path: &Path, until: Option<DateTime<Local>>) {
+    let result = match until {
+        Some(until) => fs::write(path, until.to_rfc3339()),
+        None if path.exists() => fs::remove_file(path),
+        None => Ok(()),
+    };
+
+    if let Err(error) = result {
+        error!("Failed to save pause state: {:?}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("hyprlux-pause-{}", std::process::id()));
+        let until = Local::now() + TimeDelta::minutes(30);
+
+        save(&path, Some(until));
+        assert_eq!(load(&path), Some(until));
+
+        save(&path, None);
+        assert!(!path.exists());
+        assert_eq!(load(&path), None);
+    }
+
+    #[test]
+    fn test_load_expired() {
+        let path =
+            std::env::temp_dir().join(format!("hyprlux-pause-expired-{}", std::process::id()));
+
+        save(&path, Some(Local::now() - TimeDelta::minutes(1)));
+        assert_eq!(load(&path), None);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use log::debug;
+
+/// Spawns a thread that sleeps until the next schedule boundary returned by `next_change`
+/// and calls `on_change` once it is reached. Sending on `wake` makes the scheduler
+/// recompute the boundary, e.g. after a config reload.
+pub fn spawn<N, F>(next_change: N, wake: Receiver<()>, on_change: F) -> thread::JoinHandle<()>
+where
+    N: Fn() -> Option<Duration> + Send + 'static,
+    F: Fn() + Send + 'static,
+{
+    thread::spawn(move || loop {
+        let timeout = next_change();
+        debug!("Next scheduled shader change in {:?}", timeout);
+
+        let result = match timeout {
+            Some(timeout) => wake.recv_timeout(timeout),
+            None => wake.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match result {
+            Ok(()) => debug!("Scheduler woken up, recomputing next change"),
+            Err(RecvTimeoutError::Timeout) => on_change(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc};
+
+    #[test]
+    fn test_fires_on_timeout() {
+        let (tx, rx) = mpsc::channel();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = Arc::clone(&calls);
+
+        let handle = spawn(
+            || Some(Duration::from_millis(10)),
+            rx,
+            move || {
+                calls_clone.fetch_add(1, Ordering::SeqCst);
+            },
+        );
+
+        thread::sleep(Duration::from_millis(100));
+        drop(tx);
+        handle.join().unwrap();
+
+        assert!(calls.load(Ordering::SeqCst) >= 2);
+    }
+
+    #[test]
+    fn test_wake_does_not_fire() {
+        let (tx, rx) = mpsc::channel();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = Arc::clone(&calls);
+
+        let handle = spawn(
+            || None,
+            rx,
+            move || {
+                calls_clone.fetch_add(1, Ordering::SeqCst);
+            },
+        );
+
+        tx.send(()).unwrap();
+        tx.send(()).unwrap();
+        drop(tx);
+        handle.join().unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+}
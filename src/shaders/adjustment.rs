@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use strfmt::Format;
+
+use crate::utils;
+
+use super::shader::{Shader, Stage};
+use super::window_context::WindowContext;
+
+const SHADER: &str = "
+vec3 {name}(vec3 color) {{
+    return {body};
+}}
+";
+
+/// A color adjustment given in percent, where 100 leaves the colors unchanged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Adjustment {
+    /// Dims the screen to a percentage of its brightness.
+    Brightness,
+    /// Gamma correction, higher values brighten the midtones.
+    Gamma,
+    /// Scales the contrast around mid gray.
+    Contrast,
+}
+
+/// Name, allowed percentages, stage and GLSL expression of an adjustment, in which `{value}`
+/// is the percentage as a factor.
+struct Spec {
+    name: &'static str,
+    min: i32,
+    max: i32,
+    stage: Stage,
+    body: &'static str,
+}
+
+impl Adjustment {
+    fn spec(self) -> Spec {
+        match self {
+            Adjustment::Brightness => Spec {
+                name: "brightness",
+                min: 1,
+                max: 100,
+                stage: Stage::Brightness,
+                body: "color * {value}",
+            },
+            Adjustment::Gamma => Spec {
+                name: "gamma",
+                min: 10,
+                max: 500,
+                stage: Stage::Gamma,
+                body: "pow(color, vec3(1.0 / {value}))",
+            },
+            Adjustment::Contrast => Spec {
+                name: "contrast",
+                min: 1,
+                max: 300,
+                stage: Stage::Contrast,
+                body: "clamp((color - 0.5) * {value} + 0.5, 0.0, 1.0)",
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        self.spec().name
+    }
+
+    /// Minimum and maximum percentage, values outside are clamped.
+    pub fn range(self) -> (i32, i32) {
+        let spec = self.spec();
+        (spec.min, spec.max)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct AdjustmentShader {
+    adjustment: Adjustment,
+    value: i32,
+}
+
+/// Adjustment by `value` percent, clamped to its range.
+pub fn new(adjustment: Adjustment, value: i32) -> AdjustmentShader {
+    let (min, max) = adjustment.range();
+
+    AdjustmentShader {
+        adjustment,
+        value: utils::int_in_range(value, min, max),
+    }
+}
+
+impl AdjustmentShader {
+    pub fn adjustment(&self) -> Adjustment {
+        self.adjustment
+    }
+}
+
+impl Shader for AdjustmentShader {
+    fn should_apply(&self, _context: &WindowContext) -> bool {
+        true
+    }
+
+    fn transform(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let vars = HashMap::from([
+            ("name".to_string(), name.to_string()),
+            (
+                "body".to_string(),
+                self.adjustment
+                    .spec()
+                    .body
+                    .replace("{value}", &format!("{:.2}", (self.value as f64) / 100.0)),
+            ),
+        ]);
+
+        Ok(SHADER.format(&vars)?)
+    }
+
+    fn hash(&self) -> String {
+        format!("{}_{}", self.adjustment.name(), self.value)
+    }
+
+    fn stage(&self) -> Stage {
+        self.adjustment.spec().stage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform() {
+        let shaders = [
+            (
+                new(Adjustment::Brightness, 70),
+                "brightness_70",
+                "return color * 0.70;",
+            ),
+            (
+                new(Adjustment::Brightness, 150),
+                "brightness_100",
+                "return color * 1.00;",
+            ),
+            (
+                new(Adjustment::Brightness, 0),
+                "brightness_1",
+                "return color * 0.01;",
+            ),
+            (
+                new(Adjustment::Gamma, 120),
+                "gamma_120",
+                "return pow(color, vec3(1.0 / 1.20));",
+            ),
+            (
+                new(Adjustment::Gamma, 1000),
+                "gamma_500",
+                "return pow(color, vec3(1.0 / 5.00));",
+            ),
+            (
+                new(Adjustment::Gamma, 0),
+                "gamma_10",
+                "return pow(color, vec3(1.0 / 0.10));",
+            ),
+            (
+                new(Adjustment::Contrast, 80),
+                "contrast_80",
+                "return clamp((color - 0.5) * 0.80 + 0.5, 0.0, 1.0);",
+            ),
+            (
+                new(Adjustment::Contrast, 1000),
+                "contrast_300",
+                "return clamp((color - 0.5) * 3.00 + 0.5, 0.0, 1.0);",
+            ),
+            (
+                new(Adjustment::Contrast, -5),
+                "contrast_1",
+                "return clamp((color - 0.5) * 0.01 + 0.5, 0.0, 1.0);",
+            ),
+        ];
+        for (shader, hash, expected) in shaders {
+            assert_eq!(shader.hash(), hash);
+            assert!(
+                shader.transform(hash).unwrap().contains(expected),
+                "{}",
+                hash,
+            )
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::fs;
+
+use hyprland::shared::MonitorId;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use super::shader::Shader;
+
+const HEADER: &str = "
+#version 300 es
+precision highp float;
+in vec2 v_texcoord;
+uniform sampler2D tex;
+
+layout(location = 0) out vec4 fragColor;
+";
+
+// Hyprland renders the screen shader once per monitor and sets this to the monitor ID
+const MONITOR_UNIFORM: &str = "uniform int wl_output;\n";
+
+const HASH_SEPARATOR: &str = "+";
+
+const BASELINE_HASH: &str = "baseline";
+// The baseline shader's main function is renamed to this and run before the passes
+const BASELINE_MAIN: &str = "hyprlux_baseline";
+
+/// A shader and the IDs of the monitors it runs on, `None` for all monitors.
+pub type Pass = (Box<dyn Shader>, Option<Vec<MonitorId>>);
+
+/// Chains the color transforms of several shaders into a single screen shader.
+pub struct CompositeShader {
+    shaders: Vec<Pass>,
+    /// Path of a complete screen shader the passes are applied on top of.
+    baseline: Option<String>,
+}
+
+pub fn new(mut shaders: Vec<Pass>) -> CompositeShader {
+    // Stable sort keeps the caller's order within the same stage
+    shaders.sort_by_key(|(shader, _)| shader.stage());
+
+    CompositeShader {
+        shaders,
+        baseline: None,
+    }
+}
+
+impl CompositeShader {
+    pub fn with_baseline(mut self, baseline: Option<String>) -> Self {
+        self.baseline = baseline;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shaders.is_empty()
+    }
+
+    pub fn get(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut passes = String::new();
+        let mut calls = String::new();
+
+        for (i, (shader, monitors)) in self.shaders.iter().enumerate() {
+            let name = format!("pass{}", i);
+            passes.push_str(&shader.transform(&name)?);
+            match monitors {
+                Some(monitors) => calls.push_str(&format!(
+                    "    if ({}) color = {}(color);\n",
+                    monitors
+                        .iter()
+                        .map(|id| format!("wl_output == {}", id))
+                        .collect::<Vec<String>>()
+                        .join(" || "),
+                    name
+                )),
+                None => calls.push_str(&format!("    color = {}(color);\n", name)),
+            }
+        }
+
+        let uniforms = match self.shaders.iter().any(|(_, monitors)| monitors.is_some()) {
+            true => MONITOR_UNIFORM,
+            false => "",
+        };
+
+        if let Some(baseline) = &self.baseline {
+            return on_baseline(&fs::read_to_string(baseline)?, uniforms, &passes, &calls);
+        }
+
+        Ok(format!(
+            "{}{}{}
+void main() {{
+    vec4 pixColor = texture2D(tex, v_texcoord);
+    vec3 color = vec3(pixColor[0], pixColor[1], pixColor[2]);
+{}    fragColor = vec4(color, pixColor[3]);
+}}
+",
+            HEADER, uniforms, passes, calls
+        ))
+    }
+
+    pub fn hash(&self) -> String {
+        self.baseline
+            .iter()
+            .map(|baseline| baseline_hash(baseline))
+            .chain(
+                self.shaders
+                    .iter()
+                    .map(|(shader, monitors)| match monitors {
+                        Some(monitors) => format!(
+                            "{}@{}",
+                            shader.hash(),
+                            monitors
+                                .iter()
+                                .map(|id| id.to_string())
+                                .collect::<Vec<String>>()
+                                .join(",")
+                        ),
+                        None => shader.hash(),
+                    }),
+            )
+            .collect::<Vec<String>>()
+            .join(HASH_SEPARATOR)
+    }
+}
+
+/// Identifies the baseline by a digest of its source, so that editing the file changes the
+/// hash. Falls back to the path if the file can't be read.
+fn baseline_hash(path: &str) -> String {
+    match fs::read(path) {
+        Ok(source) => format!(
+            "{}_{}",
+            BASELINE_HASH,
+            &format!("{:x}", Sha256::digest(source))[..12]
+        ),
+        Err(_) => format!("{}_{}", BASELINE_HASH, path),
+    }
+}
+
+/// Chains the passes after a complete shader by renaming its main function and reading back
+/// the color it wrote.
+fn on_baseline(
+    baseline: &str,
+    uniforms: &str,
+    passes: &str,
+    calls: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let main = Regex::new(r"void\s+main\s*\(\s*(void)?\s*\)")?;
+    if !main.is_match(baseline) {
+        return Err("Baseline shader has no main function".into());
+    }
+    let source = main.replace(baseline, format!("void {}()", BASELINE_MAIN));
+
+    // GLSL ES 3.0 shaders declare their output, older ones write gl_FragColor
+    let output = Regex::new(r"out\s+vec4\s+(\w+)\s*;")?
+        .captures(baseline)
+        .map_or("gl_FragColor".to_string(), |captures| {
+            captures[1].to_string()
+        });
+    let uniforms = match baseline.contains(uniforms.trim()) {
+        true => "",
+        false => uniforms,
+    };
+
+    Ok(format!(
+        "{}
+{}{}
+void main() {{
+    {}();
+    vec4 pixColor = {};
+    vec3 color = vec3(pixColor[0], pixColor[1], pixColor[2]);
+{}    {} = vec4(color, pixColor[3]);
+}}
+",
+        source, uniforms, passes, BASELINE_MAIN, output, calls, output
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::adjustment::{self, Adjustment};
+    use super::super::{night_light, vibrance};
+    use super::*;
+
+    fn night(temperature: i32) -> Box<dyn Shader> {
+        Box::new(
+            night_light::new(
+                true,
+                "00:00".to_string(),
+                "00:00".to_string(),
+                temperature,
+                None,
+            )
+            .unwrap(),
+        )
+    }
+
+    fn vibrance(strength: i32) -> Box<dyn Shader> {
+        Box::new(vibrance::new(strength))
+    }
+
+    fn all_monitors(shaders: Vec<Box<dyn Shader>>) -> CompositeShader {
+        new(shaders.into_iter().map(|shader| (shader, None)).collect())
+    }
+
+    #[test]
+    fn test_hash() {
+        let shaders = [
+            (vec![], ""),
+            (vec![night(3500)], "night_3500"),
+            (vec![vibrance(100)], "vibrance_100"),
+            (vec![night(3500), vibrance(100)], "night_3500+vibrance_100"),
+            (vec![vibrance(100), night(3500)], "night_3500+vibrance_100"),
+        ];
+        for (shaders, expected) in shaders {
+            assert_eq!(all_monitors(shaders).hash(), expected)
+        }
+    }
+
+    #[test]
+    fn test_get() {
+        let shader = all_monitors(vec![vibrance(100), night(3500)])
+            .get()
+            .unwrap();
+
+        assert_eq!(shader.matches("void main()").count(), 1);
+        assert!(shader.contains("vec3 pass0(vec3 color)"));
+        assert!(shader.contains("vec3 pass1(vec3 color)"));
+        // Temperature pass runs before vibrance
+        assert!(shader.contains("const float temperature = 3500.0;"));
+        assert!(
+            shader.find("color = pass0(color);").unwrap()
+                < shader.find("color = pass1(color);").unwrap()
+        );
+        assert!(
+            shader.find("const float temperature").unwrap()
+                < shader.find("const float VIB_VIBRANCE").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_stage_order() {
+        let shader = all_monitors(vec![
+            Box::new(adjustment::new(Adjustment::Brightness, 70)),
+            Box::new(adjustment::new(Adjustment::Contrast, 110)),
+            vibrance(100),
+            Box::new(adjustment::new(Adjustment::Gamma, 120)),
+            night(3500),
+        ]);
+
+        assert_eq!(
+            shader.hash(),
+            "night_3500+vibrance_100+gamma_120+contrast_110+brightness_70"
+        );
+    }
+
+    #[test]
+    fn test_monitors() {
+        let shader = new(vec![
+            (vibrance(100), Some(vec![0, 2])),
+            (vibrance(10), Some(vec![1])),
+            (night(3500), None),
+        ]);
+
+        assert_eq!(shader.hash(), "night_3500+vibrance_100@0,2+vibrance_10@1");
+
+        let shader = shader.get().unwrap();
+        assert_eq!(shader.matches("uniform int wl_output;").count(), 1);
+        assert!(shader.contains("    color = pass0(color);"));
+        assert!(shader.contains("if (wl_output == 0 || wl_output == 2) color = pass1(color);"));
+        assert!(shader.contains("if (wl_output == 1) color = pass2(color);"));
+
+        // Shaders for all monitors don't need the uniform
+        assert!(!all_monitors(vec![night(3500)])
+            .get()
+            .unwrap()
+            .contains("wl_output"));
+    }
+
+    #[test]
+    fn test_baseline() {
+        let baselines = [
+            (
+                "#version 300 es\nprecision highp float;\nin vec2 v_texcoord;\nuniform sampler2D tex;\nout vec4 outColor;\n\nvoid main() {\n    outColor = texture(tex, v_texcoord).bgra;\n}\n",
+                Some("outColor"),
+            ),
+            (
+                "precision mediump float;\nvarying vec2 v_texcoord;\nuniform sampler2D tex;\n\nvoid main(void) {\n    gl_FragColor = texture2D(tex, v_texcoord);\n}\n",
+                Some("gl_FragColor"),
+            ),
+            ("precision mediump float;\n", None),
+        ];
+        for (i, (baseline, output)) in baselines.into_iter().enumerate() {
+            let path = std::env::temp_dir().join(format!("hyprlux-test-baseline-{}", i));
+            fs::write(&path, baseline).unwrap();
+
+            let shader = all_monitors(vec![night(3500)])
+                .with_baseline(Some(path.to_str().unwrap().to_string()));
+            let hash = shader.hash();
+            assert!(hash.starts_with("baseline_"), "{}", hash);
+            assert!(hash.ends_with("+night_3500"), "{}", hash);
+
+            // Editing the baseline changes the hash
+            fs::write(&path, format!("{}\n", baseline)).unwrap();
+            assert_ne!(shader.hash(), hash);
+
+            let result = shader.get();
+            fs::remove_file(&path).unwrap();
+            let output = match output {
+                Some(output) => output,
+                None => {
+                    assert!(result.is_err(), "{}", baseline);
+                    continue;
+                }
+            };
+
+            let shader = result.unwrap();
+            assert_eq!(shader.matches("void main()").count(), 1, "{}", shader);
+            assert!(shader.contains("void hyprlux_baseline()"), "{}", shader);
+            assert!(
+                shader.contains(&format!("vec4 pixColor = {};", output)),
+                "{}",
+                shader
+            );
+            assert!(
+                shader.contains(&format!("{} = vec4(color, pixColor[3]);", output)),
+                "{}",
+                shader
+            );
+            // The baseline's own declarations are used instead of the header
+            assert!(!shader.contains("out vec4 fragColor;"), "{}", shader);
+        }
+    }
+
+    #[test]
+    fn test_is_empty() {
+        assert!(new(vec![]).is_empty());
+        assert!(!all_monitors(vec![night(3500)]).is_empty());
+    }
+}
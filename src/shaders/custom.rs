@@ -0,0 +1,133 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use log::error;
+use strfmt::Format;
+
+use super::shader::{Shader, Stage};
+use super::window_context::WindowContext;
+
+#[derive(Clone, PartialEq)]
+pub enum Source {
+    Inline(String),
+    File(PathBuf),
+}
+
+/// A user supplied GLSL color transform. The source is a `strfmt` template that must
+/// define `vec3 {name}(vec3 color)`, with literal braces escaped as `{{` and `}}`.
+#[derive(Clone, PartialEq)]
+pub struct CustomShader {
+    name: String,
+    source: Source,
+    vars: HashMap<String, String>,
+}
+
+pub fn new(name: String, source: Source, vars: HashMap<String, String>) -> CustomShader {
+    CustomShader {
+        // The name ends up in the shader file name
+        name: name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect(),
+        source,
+        vars,
+    }
+}
+
+impl CustomShader {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn source(&self) -> Result<String, Box<dyn std::error::Error>> {
+        match &self.source {
+            Source::Inline(source) => Ok(source.clone()),
+            Source::File(path) => Ok(fs::read_to_string(path)?),
+        }
+    }
+
+    /// Digest of the source and variables, so edits to the shader file are re-applied.
+    fn digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        match self.source() {
+            Ok(source) => source.hash(&mut hasher),
+            Err(error) => error!("Failed to read custom shader {}: {:?}", self.name, error),
+        }
+
+        let mut vars: Vec<(&String, &String)> = self.vars.iter().collect();
+        vars.sort();
+        vars.hash(&mut hasher);
+
+        hasher.finish()
+    }
+}
+
+impl Shader for CustomShader {
+    fn should_apply(&self, _context: &WindowContext) -> bool {
+        true
+    }
+
+    fn transform(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let mut vars = self.vars.clone();
+        vars.insert("name".to_string(), name.to_string());
+
+        Ok(self.source()?.format(&vars)?)
+    }
+
+    fn hash(&self) -> String {
+        format!("custom_{}_{:016x}", self.name, self.digest())
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Custom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAYSCALE: &str = "
+vec3 {name}(vec3 color) {{
+    return vec3(dot(color, vec3(0.2126, 0.7152, 0.0722)) * {strength});
+}}
+";
+
+    fn grayscale() -> CustomShader {
+        new(
+            "grayscale".to_string(),
+            Source::Inline(GRAYSCALE.to_string()),
+            HashMap::from([("strength".to_string(), "1.0".to_string())]),
+        )
+    }
+
+    #[test]
+    fn test_transform() {
+        let transform = grayscale().transform("pass0").unwrap();
+        assert!(transform.contains("vec3 pass0(vec3 color) {"));
+        assert!(transform.contains("vec3(0.2126, 0.7152, 0.0722)) * 1.0);"));
+    }
+
+    #[test]
+    fn test_hash_changes_with_file_contents() {
+        let path = std::env::temp_dir().join(format!("hyprlux-custom-{}.glsl", std::process::id()));
+        let shader = new(
+            "focus mode".to_string(),
+            Source::File(path.clone()),
+            HashMap::from([("strength".to_string(), "1.0".to_string())]),
+        );
+
+        fs::write(&path, GRAYSCALE).unwrap();
+        let hash = shader.hash();
+        assert!(hash.starts_with("custom_focus_mode_"));
+        assert_eq!(shader.hash(), hash);
+
+        fs::write(&path, GRAYSCALE.replace("0.2126", "0.3")).unwrap();
+        assert_ne!(shader.hash(), hash);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
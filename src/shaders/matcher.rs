@@ -0,0 +1,114 @@
+use regex::Regex;
+
+/// Matches strings exactly, by glob or by regex, optionally negated. Every mode is
+/// compiled to a regex once when the config is loaded.
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    regex: Regex,
+    negate: bool,
+}
+
+/// Matches the whole string literally.
+pub fn exact(value: &str) -> Result<Matcher, regex::Error> {
+    regex(&format!("^{}$", regex::escape(value)))
+}
+
+/// Matches the whole string, with `*` matching any run of characters and `?` any single one.
+pub fn glob(pattern: &str) -> Result<Matcher, regex::Error> {
+    let mut regex_pattern = "^".to_string();
+    for c in pattern.chars() {
+        match c {
+            '*' => regex_pattern.push_str(".*"),
+            '?' => regex_pattern.push('.'),
+            c => regex_pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex_pattern.push('$');
+
+    regex(&regex_pattern)
+}
+
+/// Matches if the regex matches anywhere in the string, anchor it to match the whole string.
+pub fn regex(pattern: &str) -> Result<Matcher, regex::Error> {
+    Ok(Matcher {
+        regex: Regex::new(pattern)?,
+        negate: false,
+    })
+}
+
+/// Matches the strings `matcher` doesn't.
+pub fn not(matcher: Matcher) -> Matcher {
+    Matcher {
+        negate: !matcher.negate,
+        ..matcher
+    }
+}
+
+impl Matcher {
+    pub fn is_match(&self, value: &str) -> bool {
+        self.regex.is_match(value) != self.negate
+    }
+
+    /// Matches if any of the values matches, negated as a whole, e.g. a workspace by ID or name.
+    pub fn is_match_any(&self, values: &[&str]) -> bool {
+        values.iter().any(|value| self.regex.is_match(value)) != self.negate
+    }
+
+    /// The regex the matcher was compiled to, before negation.
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.negate
+    }
+}
+
+// Compiled regexes can't be compared, so matchers compare by their source
+impl PartialEq for Matcher {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str() && self.negate == other.negate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_match() {
+        let matchers = [
+            ("Exact", exact("firefox"), "firefox", true),
+            ("Exact substring", exact("firefox"), "firefox-esr", false),
+            ("Exact special characters", exact("a.b"), "axb", false),
+            ("Glob star", glob("steam_app_*"), "steam_app_123", true),
+            ("Glob prefix", glob("steam_app_*"), "my_steam_app_1", false),
+            ("Glob question mark", glob("app?"), "app1", true),
+            ("Glob literal dot", glob("*.exe"), "game_exe", false),
+            ("Regex anywhere", regex("fox"), "firefox", true),
+            ("Regex anchored", regex("^fox"), "firefox", false),
+        ];
+        for (name, matcher, value, expected) in matchers {
+            let matcher = matcher.unwrap();
+            assert_eq!(matcher.is_match(value), expected, "{}", name);
+            assert_eq!(not(matcher.clone()).is_match(value), !expected, "{}", name);
+            assert_eq!(not(not(matcher)).is_match(value), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_is_match_any() {
+        let matcher = not(exact("9").unwrap());
+
+        assert!(matcher.is_match_any(&["3", "games"]));
+        // Negation applies to the values as a whole
+        assert!(!matcher.is_match_any(&["9", "gaming"]));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        assert!(regex("(firefox").is_err());
+        assert!(exact("(firefox").is_ok());
+        assert!(glob("(firefox*").is_ok());
+    }
+}
@@ -1,3 +1,14 @@
+pub mod adjustment;
+pub mod composite;
+pub mod custom;
+pub mod matcher;
+pub mod monitor;
 pub mod night_light;
+pub mod rule;
+pub mod rule_set;
 pub mod shader;
+pub mod store;
 pub mod vibrance;
+pub mod window_context;
+pub mod window_rule;
+pub mod workspace;
@@ -0,0 +1,162 @@
+use hyprland::data::Monitors;
+use hyprland::shared::{HyprData, MonitorId};
+
+use super::matcher::{self, Matcher};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorInfo {
+    pub id: MonitorId,
+    pub name: String,
+    pub description: String,
+}
+
+/// Returns the currently connected monitors.
+pub fn get() -> hyprland::Result<Vec<MonitorInfo>> {
+    Ok(Monitors::get()?
+        .into_iter()
+        .map(|monitor| MonitorInfo {
+            id: monitor.id,
+            name: monitor.name,
+            description: monitor.description,
+        })
+        .collect())
+}
+
+/// Matches monitors by a regex on their name (e.g. `DP-1`) or description.
+#[derive(Clone, PartialEq)]
+pub struct MonitorSelector {
+    monitor: Matcher,
+}
+
+pub fn new(monitor: String) -> Result<MonitorSelector, regex::Error> {
+    Ok(MonitorSelector {
+        monitor: matcher::regex(&monitor)?,
+    })
+}
+
+impl MonitorSelector {
+    pub fn matches(&self, monitor: &MonitorInfo) -> bool {
+        self.monitor
+            .is_match_any(&[&monitor.name, &monitor.description])
+    }
+}
+
+/// Assigns monitors to shaders of the same kind, so that each monitor gets the first
+/// shader whose selector matches it.
+pub struct Assignment<'a> {
+    monitors: &'a [MonitorInfo],
+    assigned: Vec<MonitorId>,
+    complete: bool,
+}
+
+pub fn assignment(monitors: &[MonitorInfo]) -> Assignment<'_> {
+    Assignment {
+        monitors,
+        assigned: Vec::new(),
+        complete: false,
+    }
+}
+
+impl Assignment<'_> {
+    /// Assigns the monitors matching `selector` that don't have a shader yet.
+    /// Returns `None` if none are left and `Some(None)` if the shader applies to all monitors.
+    pub fn assign(&mut self, selector: Option<&MonitorSelector>) -> Option<Option<Vec<MonitorId>>> {
+        if self.complete {
+            return None;
+        }
+
+        if selector.is_none() && self.assigned.is_empty() {
+            self.complete = true;
+            return Some(None);
+        }
+
+        let monitors: Vec<MonitorId> = self
+            .monitors
+            .iter()
+            .filter(|monitor| selector.is_none_or(|selector| selector.matches(monitor)))
+            .map(|monitor| monitor.id)
+            .filter(|id| !self.assigned.contains(id))
+            .collect();
+
+        if monitors.is_empty() {
+            return None;
+        }
+
+        self.assigned.extend(&monitors);
+        self.complete = self.assigned.len() == self.monitors.len();
+
+        Some(Some(monitors))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitors() -> Vec<MonitorInfo> {
+        vec![
+            MonitorInfo {
+                id: 0,
+                name: "DP-1".to_string(),
+                description: "Samsung OLED G8".to_string(),
+            },
+            MonitorInfo {
+                id: 1,
+                name: "HDMI-A-1".to_string(),
+                description: "Dell IPS".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_matches() {
+        let tests = [
+            ("DP-1", 0, true),
+            ("DP-1", 1, false),
+            ("OLED", 0, true),
+            ("^(HDMI)", 1, true),
+            ("IPS", 0, false),
+        ];
+        for (selector, monitor, expected) in tests {
+            assert_eq!(
+                new(selector.to_string())
+                    .unwrap()
+                    .matches(&monitors()[monitor]),
+                expected,
+                "{} - {}",
+                selector,
+                monitor
+            )
+        }
+    }
+
+    #[test]
+    fn test_assign() {
+        let monitors = monitors();
+        let oled = new("OLED".to_string()).unwrap();
+        let ips = new("IPS".to_string()).unwrap();
+
+        // First rule without selector takes all monitors
+        let mut all = assignment(&monitors);
+        assert_eq!(all.assign(None), Some(None));
+        assert_eq!(all.assign(Some(&oled)), None);
+        assert!(all.is_complete());
+
+        // Selectors split the monitors
+        let mut split = assignment(&monitors);
+        assert_eq!(split.assign(Some(&oled)), Some(Some(vec![0])));
+        assert_eq!(split.assign(Some(&oled)), None);
+        assert!(!split.is_complete());
+        assert_eq!(split.assign(None), Some(Some(vec![1])));
+        assert!(split.is_complete());
+
+        // Unknown monitors never match a selector
+        let mut unknown = assignment(&[]);
+        assert_eq!(unknown.assign(Some(&ips)), None);
+        assert_eq!(unknown.assign(None), Some(None));
+    }
+}
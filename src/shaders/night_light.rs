@@ -1,29 +1,19 @@
 use std::collections::HashMap;
-use std::time::{Duration, UNIX_EPOCH};
+use std::time::Duration;
 
-use chrono::{DateTime, Datelike, Local, NaiveTime};
+use chrono::{NaiveTime, TimeDelta};
 use strfmt::Format;
+use sunrise::{Coordinates, SolarDay, SolarEvent};
 
-use super::super::utils::Time;
-use super::shader::Shader;
+use super::super::utils::{self, Time};
+use super::shader::{Shader, Stage};
+use super::window_context::WindowContext;
+use super::window_rule::WindowRule;
 
 const SHADER: &str = "
-#version 300 es
-precision highp float;
-in vec2 v_texcoord;
-uniform sampler2D tex;
-
-layout(location = 0) out vec4 fragColor;
-
-const float temperature = {temperature}.0;
-const float temperatureStrength = 1.0;
-
-#define WithQuickAndDirtyLuminancePreservation
-const float LuminancePreservationFactor = 1.0;
-
 // function from https://www.shadertoy.com/view/4sc3D7
 // valid from 1000 to 40000 K (and additionally 0 for pure full white)
-vec3 colorTemperatureToRGB(const in float temperature) {{
+vec3 {name}_colorTemperatureToRGB(const in float temperature) {{
     // values from: http://blenderartists.org/forum/showthread.php?270332-OSL-Goodness&p=2268693&viewfull=1#post2268693
     mat3 m = (temperature <= 6500.0) ? mat3(vec3(0.0, -2902.1955373783176, -8257.7997278925690),
                                             vec3(0.0, 1669.5803561666639, 2575.2827530017594),
@@ -34,27 +24,45 @@ vec3 colorTemperatureToRGB(const in float temperature) {{
     return mix(clamp(vec3(m[0] / (vec3(clamp(temperature, 1000.0, 40000.0)) + m[1]) + m[2]), vec3(0.0), vec3(1.0)),
                vec3(1.0), smoothstep(1000.0, 0.0, temperature));
 }}
-void main() {{
-    vec4 pixColor = texture2D(tex, v_texcoord);
-    // RGB
-    vec3 color = vec3(pixColor[0], pixColor[1], pixColor[2]);
-#ifdef WithQuickAndDirtyLuminancePreservation
+
+vec3 {name}(vec3 color) {{
+    const float temperature = {temperature}.0;
+    const float temperatureStrength = 1.0;
+    const float LuminancePreservationFactor = 1.0;
+
+    // Quick and dirty luminance preservation
     color *= mix(1.0, dot(color, vec3(0.2126, 0.7152, 0.0722)) / max(dot(color, vec3(0.2126, 0.7152, 0.0722)), 1e-5),
                  LuminancePreservationFactor);
-#endif
-    color = mix(color, color * colorTemperatureToRGB(temperature), temperatureStrength);
-    vec4 outCol = vec4(color, pixColor[3]);
-    fragColor = outCol;
+    return mix(color, color * {name}_colorTemperatureToRGB(temperature), temperatureStrength);
 }}
 ";
 const TIME_FMT: &str = "%H:%M";
+const BOUNDARY_MARGIN: Duration = Duration::from_secs(1);
+const DAY_TEMPERATURE: i32 = 6500;
+pub const MAX_TRANSITION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Clone, PartialEq)]
+enum Schedule {
+    Manual {
+        start_time: NaiveTime,
+        end_time: NaiveTime,
+    },
+    // Sunset and sunrise are recomputed for the current date
+    Location {
+        coordinates: Coordinates,
+    },
+    // The whole day, for rules that have their own time window
+    Always,
+}
 
 #[derive(Clone, PartialEq)]
 pub struct NightLightShader {
     enabled: bool,
-    start_time: NaiveTime,
-    end_time: NaiveTime,
-    shader_vars: HashMap<String, String>,
+    schedule: Schedule,
+    temperature: i32,
+    transition_duration: Duration,
+    transition_step: i32,
+    exclude: Vec<WindowRule>,
     time_impl: Time,
 }
 
@@ -64,22 +72,16 @@ pub fn new(
     end_time: String,
     temperature: i32,
     mock_time: Option<String>,
-) -> NightLightShader {
-    let time: Time;
-    match mock_time {
-        Some(p) => time = Time::new(Some(NaiveTime::parse_from_str(&p, TIME_FMT).unwrap())),
-        None => time = Time::new(None),
-    }
-
-    let shader_vars = HashMap::from([("temperature".to_string(), temperature.to_string())]);
-
-    NightLightShader {
+) -> Result<NightLightShader, chrono::ParseError> {
+    Ok(new_with_schedule(
         enabled,
-        start_time: NaiveTime::parse_from_str(&start_time, TIME_FMT).unwrap(),
-        end_time: NaiveTime::parse_from_str(&end_time, TIME_FMT).unwrap(),
-        shader_vars,
-        time_impl: time,
-    }
+        Schedule::Manual {
+            start_time: NaiveTime::parse_from_str(&start_time, TIME_FMT)?,
+            end_time: NaiveTime::parse_from_str(&end_time, TIME_FMT)?,
+        },
+        temperature,
+        mock_time,
+    ))
 }
 
 pub fn new_from_location(
@@ -88,57 +90,209 @@ pub fn new_from_location(
     longitude: f64,
     temperature: i32,
     mock_time: Option<String>,
-) -> NightLightShader {
-    let now = Local::now();
-    let (sunrise, sunset) =
-        sunrise::sunrise_sunset(latitude, longitude, now.year(), now.month(), now.day());
+) -> Result<NightLightShader, Box<dyn std::error::Error>> {
+    let coordinates = Coordinates::new(latitude, longitude).ok_or("invalid coordinates")?;
 
-    let start_time = unix_to_string(sunset.try_into().unwrap());
-    let end_time = unix_to_string(sunrise.try_into().unwrap());
+    Ok(new_with_schedule(
+        enabled,
+        Schedule::Location { coordinates },
+        temperature,
+        mock_time,
+    ))
+}
 
-    new(enabled, start_time, end_time, temperature, mock_time)
+/// Night light that is on all day.
+pub fn new_always(temperature: i32, mock_time: Option<String>) -> NightLightShader {
+    new_with_schedule(true, Schedule::Always, temperature, mock_time)
 }
 
-fn unix_to_string(ts: u64) -> String {
-    DateTime::<Local>::from(UNIX_EPOCH + Duration::from_secs(ts))
-        .format(TIME_FMT)
-        .to_string()
+fn new_with_schedule(
+    enabled: bool,
+    schedule: Schedule,
+    temperature: i32,
+    mock_time: Option<String>,
+) -> NightLightShader {
+    let time = match mock_time {
+        Some(p) => Time::mock(&p),
+        None => Time::new(None),
+    };
+
+    NightLightShader {
+        enabled,
+        schedule,
+        temperature,
+        transition_duration: Duration::ZERO,
+        transition_step: 1,
+        exclude: Vec::new(),
+        time_impl: time,
+    }
+}
+
+impl NightLightShader {
+    /// Ramps the temperature from daylight to the target over `duration` after the start
+    /// time and back before the end time, in increments of `step` kelvin. The duration is
+    /// capped at a day.
+    pub fn with_transition(mut self, duration: Duration, step: i32) -> Self {
+        self.transition_duration = duration.min(MAX_TRANSITION);
+        self.transition_step = utils::int_in_range(step, 1, DAY_TEMPERATURE);
+        self
+    }
+
+    /// Suppresses the night light while a window matching any of the rules is focused.
+    pub fn with_exclusions(mut self, exclude: Vec<WindowRule>) -> Self {
+        self.exclude = exclude;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn set_temperature(&mut self, temperature: i32) {
+        self.temperature = temperature;
+    }
+
+    /// Returns the temperature for the current time, taking transitions into account.
+    pub fn temperature(&self) -> i32 {
+        if self.transition_duration.is_zero() {
+            return self.temperature;
+        }
+
+        let now = self.time_impl.now();
+        let (start_time, end_time) = self.window();
+
+        let elapsed = utils::until(start_time, now).num_seconds() as f64;
+        let remaining = utils::until(now, end_time).num_seconds() as f64;
+        let progress = (elapsed.min(remaining) / self.transition_duration.as_secs_f64()).min(1.0);
+
+        let delta = (self.temperature - DAY_TEMPERATURE) as f64 * progress;
+        let steps = (delta / self.transition_step as f64).round() as i32;
+
+        utils::int_in_range(
+            DAY_TEMPERATURE + steps * self.transition_step,
+            self.temperature.min(DAY_TEMPERATURE),
+            self.temperature.max(DAY_TEMPERATURE),
+        )
+    }
+
+    /// Time until the night light ends, e.g. the next sunrise in location mode.
+    pub fn until_end(&self) -> Duration {
+        let (_, end_time) = self.window();
+
+        utils::until(self.time_impl.now(), end_time)
+            .to_std()
+            .unwrap_or_default()
+    }
+
+    /// Time between two temperature steps while transitioning.
+    fn step_interval(&self) -> Duration {
+        let steps = (self.temperature - DAY_TEMPERATURE).abs() / self.transition_step;
+
+        self.transition_duration / steps.max(1) as u32
+    }
+
+    /// Returns the (start, end) times of the night light for the current date.
+    fn window(&self) -> (NaiveTime, NaiveTime) {
+        match self.schedule {
+            Schedule::Manual {
+                start_time,
+                end_time,
+            } => (start_time, end_time),
+            Schedule::Location { coordinates } => {
+                let solar_day = SolarDay::new(coordinates, self.time_impl.today());
+
+                (
+                    self.time_impl
+                        .to_local(solar_day.event_time(SolarEvent::Sunset)),
+                    self.time_impl
+                        .to_local(solar_day.event_time(SolarEvent::Sunrise)),
+                )
+            }
+            Schedule::Always => (NaiveTime::MIN, NaiveTime::MIN),
+        }
+    }
 }
 
 impl Shader for NightLightShader {
-    fn should_apply(&self, _: Option<String>, _: Option<String>) -> bool {
+    fn should_apply(&self, context: &WindowContext) -> bool {
         let now = self.time_impl.now();
 
         if !self.enabled {
             return false;
         }
 
-        if self.start_time < self.end_time {
-            return self.start_time <= now && now <= self.end_time;
+        if self.exclude.iter().any(|rule| rule.matches(context)) {
+            return false;
         }
 
-        now >= self.start_time || now <= self.end_time
+        let (start_time, end_time) = self.window();
+
+        utils::is_between(now, start_time, end_time)
     }
 
-    fn get(&self) -> Result<String, Box<dyn std::error::Error>> {
-        Ok(SHADER.format(&self.shader_vars).unwrap())
+    fn transform(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let vars = HashMap::from([
+            ("name".to_string(), name.to_string()),
+            ("temperature".to_string(), self.temperature().to_string()),
+        ]);
+
+        Ok(SHADER.format(&vars)?)
     }
 
     fn hash(&self) -> String {
-        format!("night_{}", self.shader_vars["temperature"])
+        format!("night_{}", self.temperature())
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Temperature
+    }
+
+    fn next_change(&self) -> Option<Duration> {
+        if !self.enabled || self.schedule == Schedule::Always {
+            return None;
+        }
+
+        let now = self.time_impl.now();
+        let (start_time, end_time) = self.window();
+        let transition = TimeDelta::from_std(self.transition_duration).unwrap_or_default();
+
+        let next_boundary = [
+            start_time,
+            start_time + transition,
+            end_time - transition,
+            end_time,
+        ]
+        .iter()
+        .map(|boundary| {
+            // The end time is inclusive, so wake up just after the boundary
+            utils::until(now, *boundary).to_std().unwrap() + BOUNDARY_MARGIN
+        })
+        .min();
+
+        // Step through the temperatures while transitioning
+        if self.should_apply(&WindowContext::default()) && self.temperature() != self.temperature {
+            return next_boundary.map(|boundary| boundary.min(self.step_interval()));
+        }
+
+        next_boundary
     }
 }
 
 #[cfg(test)]
-
 mod tests {
+    use super::super::matcher;
+    use super::super::window_rule::{self, Combinator};
     use super::*;
 
     #[test]
     fn test_should_apply() {
         let shaders = [
             (
-                new(false, "12:00".to_string(), "14:00".to_string(), 3500, None),
+                new(false, "12:00".to_string(), "14:00".to_string(), 3500, None).unwrap(),
                 false,
             ),
             (
@@ -148,7 +302,8 @@ mod tests {
                     "15:00".to_string(),
                     3500,
                     Some("16:00".to_string()),
-                ),
+                )
+                .unwrap(),
                 false,
             ),
             (
@@ -158,7 +313,8 @@ mod tests {
                     "15:00".to_string(),
                     3500,
                     Some("14:00".to_string()),
-                ),
+                )
+                .unwrap(),
                 true,
             ),
             (
@@ -168,7 +324,8 @@ mod tests {
                     "03:00".to_string(),
                     3500,
                     Some("04:00".to_string()),
-                ),
+                )
+                .unwrap(),
                 false,
             ),
             (
@@ -178,7 +335,8 @@ mod tests {
                     "03:00".to_string(),
                     3500,
                     Some("23:00".to_string()),
-                ),
+                )
+                .unwrap(),
                 true,
             ),
             (
@@ -188,7 +346,8 @@ mod tests {
                     "03:00".to_string(),
                     3500,
                     Some("02:00".to_string()),
-                ),
+                )
+                .unwrap(),
                 true,
             ),
             (
@@ -198,7 +357,8 @@ mod tests {
                     "03:00".to_string(),
                     3500,
                     Some("03:00".to_string()),
-                ),
+                )
+                .unwrap(),
                 true,
             ),
             (
@@ -208,28 +368,327 @@ mod tests {
                     "03:00".to_string(),
                     3500,
                     Some("22:00".to_string()),
+                )
+                .unwrap(),
+                true,
+            ),
+            (new_always(3500, Some("12:00".to_string())), true),
+        ];
+        for (shader, expected) in shaders {
+            assert_eq!(shader.should_apply(&WindowContext::default()), expected)
+        }
+    }
+
+    #[test]
+    fn test_should_apply_with_exclusions() {
+        let exclude = vec![
+            window_rule::new(matcher::regex("^(gimp)(.*)$").ok(), None),
+            window_rule::new(matcher::exact("krita").ok(), matcher::regex("krita").ok())
+                .with_combinator(Combinator::Any),
+        ];
+        let shaders = [
+            (
+                "No match",
+                (Some("class".to_string()), Some("title".to_string())),
+                true,
+            ),
+            ("No window", (None, None), true),
+            (
+                "Regex class matches",
+                (
+                    Some("gimp-2.10".to_string()),
+                    Some("GNU Image Manipulation Program".to_string()),
                 ),
+                false,
+            ),
+            ("Class matches", (Some("krita".to_string()), None), false),
+            ("Title matches", (None, Some("krita".to_string())), false),
+            (
+                "Class matches, title doesn't",
+                (Some("krita".to_string()), Some("title".to_string())),
+                false,
+            ),
+        ];
+        for (name, (class, title), expected) in shaders {
+            let shader = new(
                 true,
+                "22:00".to_string(),
+                "03:00".to_string(),
+                3500,
+                Some("23:00".to_string()),
+            )
+            .unwrap()
+            .with_exclusions(exclude.clone());
+            let context = WindowContext {
+                class: class.clone(),
+                title: title.clone(),
+                ..WindowContext::default()
+            };
+            let res = shader.should_apply(&context);
+            assert!(
+                res == expected,
+                "{} - {} - {} - {}",
+                name,
+                class.unwrap_or("".to_string()),
+                title.unwrap_or("".to_string()),
+                expected,
+            )
+        }
+    }
+
+    #[test]
+    fn test_should_apply_from_location() {
+        // Ljubljana, mocked times are in UTC
+        let (latitude, longitude) = (46.056946, 14.505751);
+        let shaders = [
+            ("2024-01-01 17:00", true),
+            ("2024-06-21 17:00", false),
+            ("2024-01-01 05:00", true),
+            ("2024-06-21 05:00", false),
+            ("2024-01-01 12:00", false),
+            ("2024-06-21 23:00", true),
+        ];
+        for (mock_time, expected) in shaders {
+            let shader =
+                new_from_location(true, latitude, longitude, 3500, Some(mock_time.to_string()))
+                    .unwrap();
+            assert_eq!(
+                shader.should_apply(&WindowContext::default()),
+                expected,
+                "{}",
+                mock_time
+            )
+        }
+    }
+
+    #[test]
+    fn test_window_changes_with_date() {
+        let (latitude, longitude) = (46.056946, 14.505751);
+        let winter = new_from_location(
+            true,
+            latitude,
+            longitude,
+            3500,
+            Some("2024-01-01 12:00".to_string()),
+        )
+        .unwrap();
+        let summer = new_from_location(
+            true,
+            latitude,
+            longitude,
+            3500,
+            Some("2024-06-21 12:00".to_string()),
+        )
+        .unwrap();
+
+        let (winter_sunset, winter_sunrise) = winter.window();
+        let (summer_sunset, summer_sunrise) = summer.window();
+        assert!(summer_sunset > winter_sunset);
+        assert!(summer_sunrise < winter_sunrise);
+    }
+
+    #[test]
+    fn test_invalid_location() {
+        let locations = [(123.0, 14.5), (46.0, 200.0), (f64::NAN, 14.5)];
+        for (latitude, longitude) in locations {
+            assert!(
+                new_from_location(true, latitude, longitude, 3500, None).is_err(),
+                "{}, {}",
+                latitude,
+                longitude
+            )
+        }
+    }
+
+    #[test]
+    fn test_invalid_time() {
+        let times = [("25:00", "06:00"), ("22:00", "6"), ("", "")];
+        for (start_time, end_time) in times {
+            assert!(
+                new(
+                    true,
+                    start_time.to_string(),
+                    end_time.to_string(),
+                    3500,
+                    None
+                )
+                .is_err(),
+                "{}-{}",
+                start_time,
+                end_time
+            )
+        }
+    }
+
+    #[test]
+    fn test_next_change() {
+        let shaders = [
+            (
+                new(false, "22:00".to_string(), "06:00".to_string(), 3500, None).unwrap(),
+                None,
+            ),
+            (
+                new(
+                    true,
+                    "22:00".to_string(),
+                    "06:00".to_string(),
+                    3500,
+                    Some("21:00".to_string()),
+                )
+                .unwrap(),
+                Some(60 * 60),
+            ),
+            (
+                new(
+                    true,
+                    "22:00".to_string(),
+                    "06:00".to_string(),
+                    3500,
+                    Some("23:00".to_string()),
+                )
+                .unwrap(),
+                Some(7 * 60 * 60),
+            ),
+            (
+                new(
+                    true,
+                    "22:00".to_string(),
+                    "06:00".to_string(),
+                    3500,
+                    Some("06:00".to_string()),
+                )
+                .unwrap(),
+                Some(0),
             ),
+            (
+                new(
+                    true,
+                    "13:00".to_string(),
+                    "15:00".to_string(),
+                    3500,
+                    Some("16:00".to_string()),
+                )
+                .unwrap(),
+                Some(21 * 60 * 60),
+            ),
+            (new_always(3500, Some("12:00".to_string())), None),
         ];
         for (shader, expected) in shaders {
-            assert_eq!(shader.should_apply(None, None), expected)
+            assert_eq!(
+                shader.next_change(),
+                expected.map(|secs| Duration::from_secs(secs) + BOUNDARY_MARGIN)
+            )
         }
     }
 
     #[test]
-    fn test_get() {
+    fn test_transition_temperature() {
+        let shaders = [
+            ("20:00", 60, 100, 6500),
+            ("20:10", 60, 100, 6000),
+            ("20:30", 60, 100, 5000),
+            ("21:00", 60, 100, 3500),
+            ("23:00", 60, 100, 3500),
+            ("05:30", 60, 100, 5000),
+            ("06:00", 60, 100, 6500),
+            ("20:20", 60, 1000, 5500),
+            ("20:30", 0, 100, 3500),
+        ];
+        for (mock_time, duration, step, expected) in shaders {
+            let shader = new(
+                true,
+                "20:00".to_string(),
+                "06:00".to_string(),
+                3500,
+                Some(mock_time.to_string()),
+            )
+            .unwrap()
+            .with_transition(Duration::from_secs(duration * 60), step);
+            assert_eq!(
+                shader.hash(),
+                format!("night_{}", expected),
+                "{} - {} - {}",
+                mock_time,
+                duration,
+                step
+            )
+        }
+    }
+
+    #[test]
+    fn test_transition_next_change() {
+        let shaders = [
+            // Before the night light starts
+            ("19:00", Duration::from_secs(60 * 60) + BOUNDARY_MARGIN),
+            // Stepping 100K every 2 minutes
+            ("20:30", Duration::from_secs(2 * 60)),
+            // Full temperature until the fade out starts
+            ("22:00", Duration::from_secs(7 * 60 * 60) + BOUNDARY_MARGIN),
+        ];
+        for (mock_time, expected) in shaders {
+            let shader = new(
+                true,
+                "20:00".to_string(),
+                "06:00".to_string(),
+                3500,
+                Some(mock_time.to_string()),
+            )
+            .unwrap()
+            .with_transition(Duration::from_secs(60 * 60), 100);
+            assert_eq!(shader.next_change(), Some(expected), "{}", mock_time)
+        }
+
+        // Overlong transitions are capped instead of overflowing
+        let shader = new(
+            true,
+            "20:00".to_string(),
+            "06:00".to_string(),
+            3500,
+            Some("20:30".to_string()),
+        )
+        .unwrap()
+        .with_transition(Duration::MAX, 100);
+        assert!(shader.next_change().is_some());
+        assert_eq!(shader.hash(), "night_6400");
+    }
+
+    #[test]
+    fn test_until_end() {
+        let shaders = [("05:00", 60 * 60), ("21:00", 9 * 60 * 60), ("06:00", 0)];
+        for (mock_time, expected) in shaders {
+            let shader = new(
+                true,
+                "20:00".to_string(),
+                "06:00".to_string(),
+                3500,
+                Some(mock_time.to_string()),
+            )
+            .unwrap();
+            assert_eq!(shader.until_end(), Duration::from_secs(expected))
+        }
+    }
+
+    #[test]
+    fn test_transform() {
         let time = "00:00".to_string();
         let shaders = [
-            (new(true, time.clone(), time.clone(), 3500, None), "3500.0"),
-            (new(true, time.clone(), time.clone(), 5000, None), "5000.0"),
-            (new(true, time.clone(), time.clone(), 1, None), "1.0"),
+            (
+                new(true, time.clone(), time.clone(), 3500, None).unwrap(),
+                "3500.0",
+            ),
+            (
+                new(true, time.clone(), time.clone(), 5000, None).unwrap(),
+                "5000.0",
+            ),
+            (
+                new(true, time.clone(), time.clone(), 1, None).unwrap(),
+                "1.0",
+            ),
         ];
         for (shader, expected) in shaders {
-            assert!(shader
-                .get()
-                .unwrap()
-                .contains(&format!("const float temperature = {};", expected)))
+            let transform = shader.transform("night").unwrap();
+            assert!(transform.contains("vec3 night(vec3 color)"));
+            assert!(transform.contains(&format!("const float temperature = {};", expected)))
         }
     }
 
@@ -238,14 +697,17 @@ mod tests {
         let time = "00:00".to_string();
         let shaders = [
             (
-                new(true, time.clone(), time.clone(), 3500, None),
+                new(true, time.clone(), time.clone(), 3500, None).unwrap(),
                 "night_3500",
             ),
             (
-                new(true, time.clone(), time.clone(), 5000, None),
+                new(true, time.clone(), time.clone(), 5000, None).unwrap(),
                 "night_5000",
             ),
-            (new(true, time.clone(), time.clone(), 1, None), "night_1"),
+            (
+                new(true, time.clone(), time.clone(), 1, None).unwrap(),
+                "night_1",
+            ),
         ];
         for (shader, expected) in shaders {
             assert_eq!(shader.hash(), expected)
@@ -0,0 +1,503 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::NaiveTime;
+
+use super::super::utils::{self, Time};
+use super::adjustment::AdjustmentShader;
+use super::composite::Pass;
+use super::custom::CustomShader;
+use super::monitor::{self, MonitorInfo, MonitorSelector};
+use super::night_light::NightLightShader;
+use super::rule_set::{self, RuleSet};
+use super::shader::Shader;
+use super::vibrance::VibranceShader;
+use super::window_context::WindowContext;
+use super::window_rule::WindowRule;
+
+const TIME_FMT: &str = "%H:%M";
+const BOUNDARY_MARGIN: Duration = Duration::from_secs(1);
+
+/// A shader applied by a rule. Rules with the same kind of effect override each other, so
+/// that each monitor gets at most one effect of each kind and one of each custom shader.
+#[derive(Clone, PartialEq)]
+pub enum Effect {
+    Temperature(NightLightShader),
+    Vibrance(VibranceShader),
+    Adjustment(AdjustmentShader),
+    Custom(CustomShader),
+}
+
+impl Effect {
+    pub fn shader(&self) -> &dyn Shader {
+        match self {
+            Effect::Temperature(shader) => shader,
+            Effect::Vibrance(shader) => shader,
+            Effect::Adjustment(shader) => shader,
+            Effect::Custom(shader) => shader,
+        }
+    }
+
+    fn boxed(&self) -> Box<dyn Shader> {
+        match self {
+            Effect::Temperature(shader) => Box::new(shader.clone()),
+            Effect::Vibrance(shader) => Box::new(shader.clone()),
+            Effect::Adjustment(shader) => Box::new(shader.clone()),
+            Effect::Custom(shader) => Box::new(shader.clone()),
+        }
+    }
+
+    /// Effects with the same key override each other.
+    fn key(&self) -> String {
+        match self {
+            Effect::Temperature(_) => "temperature".to_string(),
+            Effect::Vibrance(_) => "vibrance".to_string(),
+            Effect::Adjustment(shader) => shader.adjustment().name().to_string(),
+            Effect::Custom(shader) => format!("custom_{}", shader.name()),
+        }
+    }
+}
+
+/// Effects to apply while the focused window matches, optionally limited to a time window
+/// and to some monitors.
+#[derive(Clone, PartialEq)]
+pub struct Rule {
+    name: Option<String>,
+    window: WindowRule,
+    effects: Vec<Effect>,
+    priority: i32,
+    schedule: Option<(NaiveTime, NaiveTime)>,
+    monitor: Option<MonitorSelector>,
+    time_impl: Time,
+}
+
+pub fn new(window: WindowRule, effects: Vec<Effect>) -> Rule {
+    Rule {
+        name: None,
+        window,
+        effects,
+        priority: 0,
+        schedule: None,
+        monitor: None,
+        time_impl: Time::new(None),
+    }
+}
+
+impl Rule {
+    /// Names the rule, so that it can be found with `Rules::named`.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Rules with a higher priority override lower ones with the same effect, rules with
+    /// the same priority are tried in order.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Limits the rule to between the `HH:MM` start and end times, which may span midnight.
+    pub fn with_schedule(
+        mut self,
+        schedule: Option<(String, String)>,
+        mock_time: Option<String>,
+    ) -> Result<Self, chrono::ParseError> {
+        self.schedule = schedule
+            .map(|(start_time, end_time)| {
+                Ok((
+                    NaiveTime::parse_from_str(&start_time, TIME_FMT)?,
+                    NaiveTime::parse_from_str(&end_time, TIME_FMT)?,
+                ))
+            })
+            .transpose()?;
+        if let Some(mock_time) = mock_time {
+            self.time_impl = Time::mock(&mock_time);
+        }
+        Ok(self)
+    }
+
+    /// Limits the rule to the monitors matching `monitor`.
+    pub fn with_monitor(mut self, monitor: Option<MonitorSelector>) -> Self {
+        self.monitor = monitor;
+        self
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    fn is_scheduled(&self) -> bool {
+        match self.schedule {
+            Some((start_time, end_time)) => {
+                utils::is_between(self.time_impl.now(), start_time, end_time)
+            }
+            None => true,
+        }
+    }
+
+    /// Time until the rule's schedule or one of its effects may change on its own.
+    fn next_change(&self) -> Option<Duration> {
+        let now = self.time_impl.now();
+
+        self.schedule
+            .iter()
+            .flat_map(|(start_time, end_time)| [*start_time, *end_time])
+            .map(|boundary| utils::until(now, boundary).to_std().unwrap() + BOUNDARY_MARGIN)
+            .chain(
+                self.effects
+                    .iter()
+                    .filter_map(|effect| effect.shader().next_change()),
+            )
+            .min()
+    }
+}
+
+/// Rules ordered by priority, with their window rules indexed in a `RuleSet`.
+#[derive(Clone, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+    index: RuleSet,
+}
+
+pub fn list(mut rules: Vec<Rule>) -> Result<Rules, regex::Error> {
+    // Stable sort keeps the order of rules with the same priority
+    rules.sort_by_key(|rule| Reverse(rule.priority));
+
+    Ok(Rules {
+        index: rule_set::new(rules.iter().map(|rule| rule.window.clone()).collect())?,
+        rules,
+    })
+}
+
+impl Rules {
+    /// Shaders to apply for the context. For each kind of effect, every monitor gets it from
+    /// the highest priority rule matching the context and the monitor whose shader applies
+    /// (e.g. a night light within its schedule). `overrides` take precedence over the rules
+    /// on all monitors.
+    pub fn select(
+        &self,
+        context: &WindowContext,
+        monitors: &[MonitorInfo],
+        overrides: &[Effect],
+    ) -> Vec<Pass> {
+        let mut passes: Vec<Pass> = overrides
+            .iter()
+            .map(|effect| (effect.boxed(), None))
+            .collect();
+        let mut assignments = HashMap::new();
+
+        for i in self.index.matches(context) {
+            let rule = &self.rules[i];
+            if !rule.is_scheduled() {
+                continue;
+            }
+
+            for effect in &rule.effects {
+                let key = effect.key();
+                if overrides.iter().any(|other| other.key() == key)
+                    || !effect.shader().should_apply(context)
+                {
+                    continue;
+                }
+
+                let assignment = assignments
+                    .entry(key)
+                    .or_insert_with(|| monitor::assignment(monitors));
+                if let Some(monitors) = assignment.assign(rule.monitor.as_ref()) {
+                    passes.push((effect.boxed(), monitors));
+                }
+            }
+        }
+
+        passes
+    }
+
+    /// Time until any rule may change on its own.
+    pub fn next_change(&self) -> Option<Duration> {
+        self.rules.iter().filter_map(Rule::next_change).min()
+    }
+
+    /// The effects of all rules, in priority order.
+    pub fn effects(&self) -> impl Iterator<Item = &Effect> {
+        self.rules.iter().flat_map(|rule| &rule.effects)
+    }
+
+    pub fn named(&self, name: &str) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.name.as_deref() == Some(name))
+    }
+
+    pub fn effects_mut(&mut self) -> impl Iterator<Item = &mut Effect> {
+        self.rules.iter_mut().flat_map(|rule| &mut rule.effects)
+    }
+}
+
+impl PartialEq for Rules {
+    fn eq(&self, other: &Self) -> bool {
+        self.rules == other.rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::adjustment::{self, Adjustment};
+    use super::super::{custom, matcher, night_light, vibrance, window_rule};
+    use super::*;
+
+    fn class(window_class: &str) -> WindowRule {
+        window_rule::new(matcher::exact(window_class).ok(), None)
+    }
+
+    fn vibrance(strength: i32) -> Vec<Effect> {
+        vec![Effect::Vibrance(vibrance::new(strength))]
+    }
+
+    fn window(window_class: &str) -> WindowContext {
+        WindowContext {
+            class: Some(window_class.to_string()),
+            ..WindowContext::default()
+        }
+    }
+
+    fn hashes(passes: &[Pass]) -> Vec<String> {
+        passes.iter().map(|(shader, _)| shader.hash()).collect()
+    }
+
+    #[test]
+    fn test_priority() {
+        let rules = list(vec![
+            new(window_rule::any(), vibrance(10)),
+            new(class("firefox"), vibrance(20)),
+            new(class("steam"), vibrance(30)).with_priority(10),
+            new(window_rule::any(), vibrance(40)).with_priority(-1),
+            new(window_rule::any(), vibrance(50)).with_priority(i32::MIN),
+        ])
+        .unwrap();
+
+        let contexts = [
+            ("First matching rule", window("firefox"), "vibrance_10"),
+            ("Higher priority", window("steam"), "vibrance_30"),
+            ("No window", WindowContext::default(), "vibrance_10"),
+        ];
+        for (name, context, expected) in contexts {
+            assert_eq!(
+                hashes(&rules.select(&context, &[], &[])),
+                vec![expected],
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_effects() {
+        let grayscale = || {
+            Effect::Custom(custom::new(
+                "grayscale".to_string(),
+                custom::Source::Inline(String::new()),
+                HashMap::new(),
+            ))
+        };
+        let rules = list(vec![
+            new(
+                window_rule::any(),
+                vec![Effect::Temperature(night_light::new_always(3500, None))],
+            ),
+            new(window_rule::any(), vibrance(10)),
+            new(class("firefox"), vec![grayscale()]).with_priority(5),
+            new(
+                class("firefox"),
+                vec![
+                    Effect::Temperature(night_light::new_always(4500, None)),
+                    grayscale(),
+                ],
+            )
+            .with_priority(1),
+        ])
+        .unwrap();
+
+        // Different kinds of effects are combined, the same kind only applies once
+        let mut firefox = hashes(&rules.select(&window("firefox"), &[], &[]));
+        firefox.sort();
+        assert_eq!(firefox.len(), 3);
+        assert!(firefox[0].starts_with("custom_grayscale_"));
+        assert_eq!(firefox[1..], ["night_4500", "vibrance_10"]);
+
+        // Tone adjustments combine with the temperature, e.g. dimming at night
+        let tone = list(vec![
+            new(
+                window_rule::any(),
+                vec![Effect::Temperature(night_light::new_always(3500, None))],
+            ),
+            new(
+                window_rule::any(),
+                vec![Effect::Adjustment(adjustment::new(
+                    Adjustment::Brightness,
+                    70,
+                ))],
+            )
+            .with_schedule(
+                Some(("23:00".to_string(), "06:00".to_string())),
+                Some("23:30".to_string()),
+            )
+            .unwrap(),
+            new(
+                window_rule::any(),
+                vec![Effect::Adjustment(adjustment::new(Adjustment::Gamma, 120))],
+            ),
+            new(
+                window_rule::any(),
+                vec![Effect::Adjustment(adjustment::new(Adjustment::Gamma, 90))],
+            ),
+        ])
+        .unwrap();
+        assert_eq!(
+            hashes(&tone.select(&window("code"), &[], &[])),
+            vec!["night_3500", "brightness_70", "gamma_120"]
+        );
+
+        // Overrides replace the effect of the rules
+        assert_eq!(
+            hashes(&rules.select(&window("code"), &[], &vibrance(50))),
+            vec!["vibrance_50", "night_3500"]
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        let rules = [
+            ("No schedule", None, "12:00", true),
+            (
+                "Inside time window",
+                Some(("22:00", "06:00")),
+                "23:00",
+                true,
+            ),
+            (
+                "Outside time window",
+                Some(("22:00", "06:00")),
+                "12:00",
+                false,
+            ),
+            (
+                "End time is inclusive",
+                Some(("22:00", "06:00")),
+                "06:00",
+                true,
+            ),
+        ];
+        for (name, schedule, now, expected) in rules {
+            let rules = list(vec![new(window_rule::any(), vibrance(10))
+                .with_schedule(
+                    schedule.map(|(start_time, end_time)| {
+                        (start_time.to_string(), end_time.to_string())
+                    }),
+                    Some(now.to_string()),
+                )
+                .unwrap()])
+            .unwrap();
+
+            assert_eq!(
+                !rules.select(&WindowContext::default(), &[], &[]).is_empty(),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_schedule() {
+        let schedules = [("25:00", "06:00"), ("22:00", "6"), ("", "")];
+        for (start_time, end_time) in schedules {
+            assert!(
+                new(window_rule::any(), vibrance(10))
+                    .with_schedule(Some((start_time.to_string(), end_time.to_string())), None)
+                    .is_err(),
+                "{}-{}",
+                start_time,
+                end_time
+            );
+        }
+    }
+
+    #[test]
+    fn test_unscheduled_effect() {
+        // A night light outside its own schedule leaves the temperature to other rules
+        let night = night_light::new(
+            true,
+            "22:00".to_string(),
+            "06:00".to_string(),
+            3500,
+            Some("12:00".to_string()),
+        )
+        .unwrap();
+        let rules = list(vec![
+            new(window_rule::any(), vec![Effect::Temperature(night)]).with_priority(1),
+            new(
+                window_rule::any(),
+                vec![Effect::Temperature(night_light::new_always(5000, None))],
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            hashes(&rules.select(&WindowContext::default(), &[], &[])),
+            vec!["night_5000"]
+        );
+    }
+
+    #[test]
+    fn test_monitors() {
+        let monitors = vec![
+            MonitorInfo {
+                id: 0,
+                name: "DP-1".to_string(),
+                description: "Samsung OLED G8".to_string(),
+            },
+            MonitorInfo {
+                id: 1,
+                name: "HDMI-A-1".to_string(),
+                description: "Dell IPS".to_string(),
+            },
+        ];
+        let rules = list(vec![
+            new(class("steam"), vibrance(40)).with_monitor(monitor::new("OLED".to_string()).ok()),
+            new(class("steam"), vibrance(100)),
+            new(window_rule::any(), vibrance(10)),
+        ])
+        .unwrap();
+
+        let passes = rules.select(&window("steam"), &monitors, &[]);
+        let monitors: Vec<Option<Vec<i128>>> = passes
+            .iter()
+            .map(|(_, monitors)| monitors.clone())
+            .collect();
+        assert_eq!(hashes(&passes), vec!["vibrance_40", "vibrance_100"]);
+        assert_eq!(monitors, vec![Some(vec![0]), Some(vec![1])]);
+    }
+
+    #[test]
+    fn test_next_change() {
+        let rules = list(vec![
+            new(window_rule::any(), vibrance(10)),
+            new(window_rule::any(), vibrance(20))
+                .with_schedule(
+                    Some(("22:00".to_string(), "06:00".to_string())),
+                    Some("21:00".to_string()),
+                )
+                .unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(
+            rules.next_change(),
+            Some(Duration::from_secs(60 * 60) + BOUNDARY_MARGIN)
+        );
+
+        let rules = list(vec![new(window_rule::any(), vibrance(10))]).unwrap();
+        assert_eq!(rules.next_change(), None);
+    }
+}
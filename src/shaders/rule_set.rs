@@ -0,0 +1,179 @@
+use regex::RegexSet;
+
+use super::matcher::Matcher;
+use super::window_context::WindowContext;
+use super::window_rule::WindowRule;
+
+/// Window rules indexed by their class and title matchers, so that finding the rules
+/// matching a window runs two `RegexSet`s instead of every rule's regexes.
+#[derive(Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<WindowRule>,
+    classes: Index,
+    titles: Index,
+}
+
+/// The set matchers of the rules in a `RegexSet`, negated ones are flipped after matching.
+/// Empty regexes match anything and are left out, as they make the set fall back to a much
+/// slower search.
+#[derive(Clone, Default)]
+struct Index {
+    set: RegexSet,
+    /// Rule of each regex in the set.
+    rules: Vec<usize>,
+    /// Whether each rule's matcher is negated.
+    negated: Vec<bool>,
+    /// Whether each rule's matcher matches a value none of the set's regexes match.
+    unmatched: Vec<bool>,
+}
+
+fn index<'a>(matchers: impl Iterator<Item = Option<&'a Matcher>>) -> Result<Index, regex::Error> {
+    let mut rules = Vec::new();
+    let mut patterns = Vec::new();
+    let mut negated = Vec::new();
+    let mut unmatched = Vec::new();
+    for (i, matcher) in matchers.enumerate() {
+        let negate = matcher.is_some_and(Matcher::is_negated);
+        negated.push(negate);
+        unmatched.push(match matcher.map(Matcher::as_str) {
+            None => true,
+            Some("") => !negate,
+            Some(pattern) => {
+                rules.push(i);
+                patterns.push(pattern);
+                negate
+            }
+        });
+    }
+
+    Ok(Index {
+        set: RegexSet::new(patterns)?,
+        rules,
+        negated,
+        unmatched,
+    })
+}
+
+impl Index {
+    /// Whether each rule's matcher matches the value. Unset matchers always match, set ones
+    /// never match a missing value.
+    fn matches(&self, value: Option<&String>) -> Vec<bool> {
+        let value = match value {
+            Some(value) => value,
+            None => return vec![false; self.unmatched.len()],
+        };
+
+        let mut matches = self.unmatched.clone();
+        for i in self.set.matches(value).iter() {
+            let rule = self.rules[i];
+            matches[rule] = !self.negated[rule];
+        }
+
+        matches
+    }
+}
+
+pub fn new(rules: Vec<WindowRule>) -> Result<RuleSet, regex::Error> {
+    Ok(RuleSet {
+        classes: index(rules.iter().map(WindowRule::window_class))?,
+        titles: index(rules.iter().map(WindowRule::window_title))?,
+        rules,
+    })
+}
+
+impl RuleSet {
+    /// Indices of the rules matching the context, in order.
+    pub fn matches(&self, context: &WindowContext) -> Vec<usize> {
+        let classes = self.classes.matches(context.class.as_ref());
+        let titles = self.titles.matches(context.title.as_ref());
+
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(i, rule)| rule.matches_with(context, classes[*i], titles[*i]))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+impl PartialEq for RuleSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.rules == other.rules
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::matcher;
+    use super::super::window_rule::{self, Combinator};
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let rules = [
+            (matcher::glob("steam_app_*").ok(), None, Combinator::All),
+            (
+                matcher::exact("firefox").ok(),
+                matcher::regex("firefox").ok(),
+                Combinator::All,
+            ),
+            (
+                matcher::exact("firefox").ok(),
+                matcher::regex("firefox").ok(),
+                Combinator::Any,
+            ),
+            (
+                None,
+                matcher::regex("^(Some Epic)(.*)$").ok(),
+                Combinator::All,
+            ),
+            (
+                Some(matcher::not(matcher::exact("firefox").unwrap())),
+                None,
+                Combinator::All,
+            ),
+            (matcher::regex("").ok(), None, Combinator::All),
+            (None, None, Combinator::All),
+            (
+                Some(matcher::not(matcher::regex("").unwrap())),
+                None,
+                Combinator::Any,
+            ),
+        ];
+        let rule_set = new(rules
+            .iter()
+            .map(|(class, title, combinator)| {
+                window_rule::new(class.clone(), title.clone()).with_combinator(*combinator)
+            })
+            .collect())
+        .unwrap();
+
+        let contexts = [
+            (
+                Some("steam_app_123"),
+                Some("Some Epic Game"),
+                vec![0, 3, 4, 5, 6],
+            ),
+            (Some("firefox"), Some("Mozilla Firefox"), vec![2, 5, 6]),
+            (Some("firefox"), Some("firefox"), vec![1, 2, 5, 6]),
+            // Set matchers never match a missing class or title
+            (Some("firefox"), None, vec![2, 5, 6]),
+            (None, Some("firefox"), vec![2, 6]),
+            (None, None, vec![6]),
+        ];
+        for (class, title, expected) in contexts {
+            let context = WindowContext {
+                class: class.map(str::to_string),
+                title: title.map(str::to_string),
+                ..WindowContext::default()
+            };
+            assert_eq!(rule_set.matches(&context), expected, "{:?}", context);
+
+            // Same result as matching every rule on its own
+            let unindexed: Vec<usize> = (0..rules.len())
+                .filter(|i| rule_set.rules[*i].matches(&context))
+                .collect();
+            assert_eq!(unindexed, expected, "{:?}", context);
+        }
+    }
+}
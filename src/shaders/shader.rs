@@ -1,47 +1,156 @@
-use std::env;
-use std::fs::File;
-use std::io::Write;
+use std::fmt;
+use std::time::Duration;
 
-use super::super::utils;
-use hyprland::keyword::Keyword;
+use super::super::compositor::Compositor;
+use super::composite::CompositeShader;
+use super::store::ShaderStore;
+use super::window_context::WindowContext;
 use log::info;
 
-const SHADER_KEY: &str = "decoration:screen_shader";
-const NO_SHADER: &str = "[[EMPTY]]";
+/// Order in which shader passes are chained when composed into a single program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Temperature,
+    Color,
+    Gamma,
+    Contrast,
+    Brightness,
+    Custom,
+}
 
 pub trait Shader {
-    fn should_apply(&self, window_class: Option<String>, window_title: Option<String>) -> bool;
-    fn get(&self) -> Result<String, Box<dyn std::error::Error>>;
+    fn should_apply(&self, context: &WindowContext) -> bool;
+    /// GLSL source defining a `vec3 {name}(vec3 color)` color transform function.
+    fn transform(&self, name: &str) -> Result<String, Box<dyn std::error::Error>>;
     fn hash(&self) -> String;
+    fn stage(&self) -> Stage;
+    /// Time until `should_apply` may change without any window focus change.
+    fn next_change(&self) -> Option<Duration> {
+        None
+    }
 }
 
-pub fn apply(shader: &dyn Shader) -> Result<(), Box<dyn std::error::Error>> {
-    info!("Applying shader: {}", shader.hash());
+/// A screen shader written and set by hyprlux.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppliedShader {
+    pub hash: String,
+    pub path: String,
+}
+
+/// What hyprlux knows about the screen shader, so that focus changes don't have to ask the
+/// compositor.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ScreenShader {
+    /// Not checked yet, e.g. at startup or after Hyprland reloaded its config.
+    #[default]
+    Unknown,
+    /// Set by hyprlux, `None` if hyprlux's shader is removed and the baseline shows.
+    Applied(Option<AppliedShader>),
+    /// Set by another tool while hyprlux runs.
+    External(String),
+}
+
+impl ScreenShader {
+    /// Whether the screen shader is known to be the one with `hash`, or no shader for `None`.
+    pub fn is_applied(&self, hash: Option<&str>) -> bool {
+        match self {
+            ScreenShader::Applied(applied) => {
+                applied.as_ref().map(|applied| applied.hash.as_str()) == hash
+            }
+            _ => false,
+        }
+    }
 
-    let output = shader.get().unwrap();
+    /// Identifies the compositor's current screen shader `path` against what was known.
+    pub fn identify(&self, path: Option<String>, baseline: Option<&str>) -> ScreenShader {
+        match (path, self) {
+            (None, _) => ScreenShader::Applied(None),
+            (Some(path), _) if Some(path.as_str()) == baseline => ScreenShader::Applied(None),
+            (Some(path), ScreenShader::Applied(Some(applied))) if applied.path == path => {
+                self.clone()
+            }
+            (Some(path), _) => ScreenShader::External(path),
+        }
+    }
+}
+
+impl fmt::Display for ScreenShader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenShader::Unknown => write!(f, "unknown"),
+            ScreenShader::Applied(Some(applied)) => write!(f, "{}", applied.hash),
+            ScreenShader::Applied(None) => write!(f, "none"),
+            ScreenShader::External(path) => write!(f, "external ({})", path),
+        }
+    }
+}
 
-    let path = env::temp_dir().join(shader.hash()).to_owned();
+pub fn apply(
+    compositor: &dyn Compositor,
+    store: &ShaderStore,
+    shader: &CompositeShader,
+) -> Result<AppliedShader, Box<dyn std::error::Error>> {
+    info!("Applying shader: {}", shader.hash());
 
-    let mut shader_file = File::create(path.clone())?;
-    shader_file.write_all(output.as_bytes())?;
+    let path = store.write(&shader.hash(), &shader.get()?)?;
+    compositor.set_screen_shader(Some(&path))?;
 
-    remove().unwrap();
-    Ok(Keyword::set(
-        SHADER_KEY,
-        path.into_os_string().into_string().unwrap(),
-    )?)
+    Ok(AppliedShader {
+        hash: shader.hash(),
+        path,
+    })
 }
 
-pub fn remove() -> Result<(), Box<dyn std::error::Error>> {
-    Ok(Keyword::set(SHADER_KEY, NO_SHADER)?)
+/// Restores the `baseline` shader hyprlux started with, or no shader.
+pub fn remove(
+    compositor: &dyn Compositor,
+    baseline: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    compositor.set_screen_shader(baseline)
 }
 
-pub fn get() -> Option<String> {
-    let shader = Keyword::get(SHADER_KEY).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if shader.value.to_string() == NO_SHADER {
-        return None;
-    }
+    #[test]
+    fn test_identify() {
+        let applied = ScreenShader::Applied(Some(AppliedShader {
+            hash: "night_3500".to_string(),
+            path: "/tmp/night_3500".to_string(),
+        }));
+        let tests = [
+            (&applied, Some("/tmp/night_3500"), applied.clone()),
+            (&applied, None, ScreenShader::Applied(None)),
+            (
+                &applied,
+                Some("/tmp/vibrance_100"),
+                ScreenShader::External("/tmp/vibrance_100".to_string()),
+            ),
+            // Unknown shaders are never taken for hyprlux's own
+            (
+                &ScreenShader::Unknown,
+                Some("/tmp/night_3500"),
+                ScreenShader::External("/tmp/night_3500".to_string()),
+            ),
+            (&ScreenShader::Unknown, None, ScreenShader::Applied(None)),
+        ];
+        for (known, path, expected) in tests {
+            assert_eq!(
+                known.identify(path.map(str::to_string), None),
+                expected,
+                "{:?}",
+                path
+            )
+        }
 
-    Some(utils::shader_hash_from_path(shader.value.to_string()).unwrap())
+        // The baseline is not an external shader
+        assert_eq!(
+            applied.identify(
+                Some("/etc/hypr/base.frag".to_string()),
+                Some("/etc/hypr/base.frag")
+            ),
+            ScreenShader::Applied(None)
+        );
+    }
 }
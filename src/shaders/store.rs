@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+use std::fs::{self, DirBuilder, OpenOptions, Permissions};
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use log::{debug, error};
+use sha2::{Digest, Sha256};
+
+const DIR_NAME: &str = "hyprlux";
+const MANIFEST: &str = "manifest";
+// Manifest entry for the baseline, never the name of a shader file
+const BASELINE: &str = "baseline";
+const EXTENSION: &str = "frag";
+const TMP_EXTENSION: &str = "tmp";
+
+/// Shader files written by hyprlux, named by a digest of their source and kept in a private
+/// directory together with a manifest of the shader hash each file holds and the baseline
+/// to restore.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShaderStore {
+    /// `None` for a directory in `$XDG_RUNTIME_DIR`, created on first use.
+    dir: Option<PathBuf>,
+}
+
+/// Store in `dir` instead of `$XDG_RUNTIME_DIR`.
+pub fn new(dir: PathBuf) -> ShaderStore {
+    ShaderStore { dir: Some(dir) }
+}
+
+impl ShaderStore {
+    /// Writes the shader source unless a file with the same source exists already.
+    /// Returns the file's path.
+    pub fn write(&self, hash: &str, source: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let dir = self.dir()?;
+        let name = format!("{:x}.{}", Sha256::digest(source), EXTENSION);
+        let path = dir.join(&name);
+
+        if !path.exists() {
+            write_atomic(&path, source)?;
+        }
+
+        let mut manifest = read_manifest(&dir);
+        manifest.shaders.insert(name, hash.to_string());
+        write_manifest(&dir, &manifest)?;
+
+        Ok(path
+            .to_str()
+            .ok_or("Shader path is not valid UTF-8")?
+            .to_string())
+    }
+
+    /// Hash of the shader at `path` if it was written to this store.
+    pub fn hash(&self, path: &str) -> Option<String> {
+        let dir = self.dir().ok()?;
+        let path = Path::new(path);
+        if path.parent() != Some(dir.as_path()) {
+            return None;
+        }
+
+        read_manifest(&dir)
+            .shaders
+            .remove(path.file_name()?.to_str()?)
+    }
+
+    /// Baseline recorded by `set_baseline`, also by a run that didn't exit cleanly.
+    pub fn baseline(&self) -> Option<String> {
+        read_manifest(&self.dir().ok()?).baseline
+    }
+
+    /// Records the screen shader to restore when hyprlux's own is removed.
+    pub fn set_baseline(&self, baseline: Option<&str>) {
+        let dir = match self.dir() {
+            Ok(dir) => dir,
+            Err(error) => return error!("Failed to open shader store: {:?}", error),
+        };
+
+        let mut manifest = read_manifest(&dir);
+        manifest.baseline = baseline.map(str::to_string);
+        if let Err(error) = write_manifest(&dir, &manifest) {
+            error!("Failed to write shader manifest: {:?}", error);
+        }
+    }
+
+    /// Removes every shader file except the one at `keep`, including ones missing from the
+    /// manifest and temporary files left behind by a crash.
+    pub fn retain(&self, keep: Option<&str>) {
+        let dir = match self.dir() {
+            Ok(dir) => dir,
+            Err(error) => return error!("Failed to open shader store: {:?}", error),
+        };
+        let keep = keep.and_then(|keep| Path::new(keep).file_name());
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(error) => return error!("Failed to list shader store: {:?}", error),
+        };
+        for path in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            let is_shader = path
+                .extension()
+                .is_some_and(|extension| extension == EXTENSION || extension == TMP_EXTENSION);
+            if !is_shader || path.file_name() == keep {
+                continue;
+            }
+
+            debug!("Removing shader file {}", path.display());
+            if let Err(error) = fs::remove_file(&path) {
+                if error.kind() != io::ErrorKind::NotFound {
+                    error!(
+                        "Failed to remove shader file {}: {:?}",
+                        path.display(),
+                        error
+                    );
+                }
+            }
+        }
+
+        let mut manifest = read_manifest(&dir);
+        manifest
+            .shaders
+            .retain(|name, _| keep.is_some_and(|keep| keep == name.as_str()));
+
+        if let Err(error) = write_manifest(&dir, &manifest) {
+            error!("Failed to write shader manifest: {:?}", error);
+        }
+    }
+
+    /// The store's directory, created with only the user allowed in.
+    fn dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let dir = match &self.dir {
+            Some(dir) => dir.clone(),
+            None => xdg::BaseDirectories::new()?
+                .get_runtime_directory()?
+                .join(DIR_NAME),
+        };
+
+        match fs::symlink_metadata(&dir) {
+            Ok(metadata) => {
+                let owner = fs::metadata(dir.parent().ok_or("Shader store has no parent")?)?;
+                if !metadata.is_dir() || metadata.uid() != owner.uid() {
+                    return Err(
+                        format!("{} is not a directory owned by the user", dir.display()).into(),
+                    );
+                }
+                if metadata.mode() & 0o077 != 0 {
+                    fs::set_permissions(&dir, Permissions::from_mode(0o700))?;
+                }
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                DirBuilder::new().mode(0o700).recursive(true).create(&dir)?;
+            }
+            Err(error) => return Err(error.into()),
+        }
+
+        Ok(dir)
+    }
+}
+
+/// Writes to a temporary file first, so that Hyprland never reads a partial file.
+fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let tmp = path.with_extension(TMP_EXTENSION);
+    let _ = fs::remove_file(&tmp);
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&tmp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+
+    fs::rename(&tmp, path)
+}
+
+#[derive(Default)]
+struct Manifest {
+    /// File names and the shader hash each holds.
+    shaders: BTreeMap<String, String>,
+    baseline: Option<String>,
+}
+
+/// One `{name} {hash}` pair per line, and `baseline {path}`.
+fn read_manifest(dir: &Path) -> Manifest {
+    let mut manifest = Manifest::default();
+
+    for (name, value) in fs::read_to_string(dir.join(MANIFEST))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+    {
+        match name {
+            BASELINE => manifest.baseline = Some(value.to_string()),
+            name => {
+                manifest.shaders.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    manifest
+}
+
+fn write_manifest(dir: &Path, manifest: &Manifest) -> io::Result<()> {
+    if manifest.shaders.is_empty() && manifest.baseline.is_none() {
+        return match fs::remove_file(dir.join(MANIFEST)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        };
+    }
+
+    write_atomic(
+        &dir.join(MANIFEST),
+        &manifest
+            .baseline
+            .iter()
+            .map(|baseline| format!("{} {}\n", BASELINE, baseline))
+            .chain(
+                manifest
+                    .shaders
+                    .iter()
+                    .map(|(name, hash)| format!("{} {}\n", name, hash)),
+            )
+            .collect::<String>(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(name: &str) -> ShaderStore {
+        let dir = std::env::temp_dir().join(format!(
+            "hyprlux-test-store-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        new(dir)
+    }
+
+    #[test]
+    fn test_write() {
+        let store = store("write");
+        let path = store.write("night_3500", "void main() {}").unwrap();
+        let dir = store.dir.clone().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "void main() {}");
+        assert_eq!(
+            fs::metadata(&dir).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        // Same source, same file
+        assert_eq!(store.write("night_3500", "void main() {}").unwrap(), path);
+        assert_ne!(
+            store.write("vibrance_100", "void main() { }").unwrap(),
+            path
+        );
+
+        assert_eq!(store.hash(&path), Some("night_3500".to_string()));
+        assert_eq!(store.hash("/home/user/night_3500"), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_retain() {
+        let store = store("retain");
+        let night = store.write("night_3500", "night").unwrap();
+        let vibrance = store.write("vibrance_100", "vibrance").unwrap();
+        let dir = store.dir.clone().unwrap();
+
+        store.retain(Some(&vibrance));
+        assert!(!Path::new(&night).exists());
+        assert_eq!(store.hash(&night), None);
+        assert_eq!(store.hash(&vibrance), Some("vibrance_100".to_string()));
+
+        store.retain(None);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        // Files missing from the manifest, e.g. after a crash, are removed as well
+        let kept = store.write("night_3500", "night").unwrap();
+        fs::write(dir.join("0123.tmp"), "partial").unwrap();
+        fs::write(dir.join("4567.frag"), "unlisted").unwrap();
+        fs::write(dir.join("notes.txt"), "not a shader").unwrap();
+        store.retain(Some(&kept));
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                Path::new(&kept).file_name().unwrap().to_str().unwrap(),
+                MANIFEST,
+                "notes.txt"
+            ]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_baseline() {
+        let store = store("baseline");
+        let dir = store.dir.clone().unwrap();
+        assert_eq!(store.baseline(), None);
+
+        store.set_baseline(Some("/home/user/.config/hypr/my shader.frag"));
+        let night = store.write("night_3500", "night").unwrap();
+        assert_eq!(
+            store.baseline().as_deref(),
+            Some("/home/user/.config/hypr/my shader.frag")
+        );
+
+        // Removing the shaders keeps the baseline
+        store.retain(None);
+        assert!(!Path::new(&night).exists());
+        assert_eq!(
+            store.baseline().as_deref(),
+            Some("/home/user/.config/hypr/my shader.frag")
+        );
+
+        store.set_baseline(None);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_insecure_dir() {
+        let store = store("insecure");
+        let dir = store.dir.clone().unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        fs::set_permissions(&dir, Permissions::from_mode(0o777)).unwrap();
+
+        store.write("night_3500", "night").unwrap();
+        assert_eq!(
+            fs::metadata(&dir).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+
+        // A symlink could point anywhere
+        let link = dir.with_extension("link");
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert!(new(link.clone()).write("night_3500", "night").is_err());
+
+        fs::remove_file(link).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,47 @@
+use hyprland::data::{Client, FullscreenMode};
+use hyprland::shared::HyprDataActiveOptional;
+
+use super::workspace::{self, WorkspaceInfo};
+
+/// The focused window and workspace that shader rules are matched against. Window fields
+/// are unset (or false) when no window is focused.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowContext {
+    pub class: Option<String>,
+    pub title: Option<String>,
+    pub initial_class: Option<String>,
+    pub initial_title: Option<String>,
+    pub pid: Option<i32>,
+    pub fullscreen: bool,
+    pub floating: bool,
+    pub xwayland: bool,
+    pub pinned: bool,
+    pub workspace: Option<WorkspaceInfo>,
+}
+
+/// Returns the context of the focused window.
+pub fn get_active() -> hyprland::Result<WindowContext> {
+    let workspace = Some(workspace::get_active()?);
+
+    Ok(match Client::get_active()? {
+        Some(client) => WindowContext {
+            class: Some(client.class),
+            title: Some(client.title),
+            initial_class: Some(client.initial_class),
+            initial_title: Some(client.initial_title),
+            pid: Some(client.pid),
+            fullscreen: matches!(
+                client.fullscreen,
+                FullscreenMode::Fullscreen | FullscreenMode::MaximizedFullscreen
+            ),
+            floating: client.floating,
+            xwayland: client.xwayland,
+            pinned: client.pinned,
+            workspace,
+        },
+        None => WindowContext {
+            workspace,
+            ..WindowContext::default()
+        },
+    })
+}
@@ -0,0 +1,425 @@
+use super::matcher::Matcher;
+use super::window_context::WindowContext;
+
+/// Window properties a rule requires, unset ones match any window.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowConditions {
+    pub initial_class: Option<Matcher>,
+    pub initial_title: Option<Matcher>,
+    pub pid: Option<i32>,
+    pub fullscreen: Option<bool>,
+    pub floating: Option<bool>,
+    pub xwayland: Option<bool>,
+    pub pinned: Option<bool>,
+}
+
+impl WindowConditions {
+    fn matches(&self, context: &WindowContext) -> bool {
+        self.pid.is_none_or(|pid| context.pid == Some(pid))
+            && matcher_matches(&self.initial_class, &context.initial_class)
+            && matcher_matches(&self.initial_title, &context.initial_title)
+            && [
+                (self.fullscreen, context.fullscreen),
+                (self.floating, context.floating),
+                (self.xwayland, context.xwayland),
+                (self.pinned, context.pinned),
+            ]
+            .iter()
+            .all(|(expected, actual)| expected.is_none_or(|expected| expected == *actual))
+    }
+}
+
+/// Unset matchers match anything, set ones never match a missing value, even negated.
+fn matcher_matches(matcher: &Option<Matcher>, value: &Option<String>) -> bool {
+    match matcher {
+        Some(matcher) => value.as_ref().is_some_and(|value| matcher.is_match(value)),
+        None => true,
+    }
+}
+
+/// How the class and title matchers of a rule combine.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Combinator {
+    /// Every set matcher must match.
+    #[default]
+    All,
+    /// At least one set matcher must match.
+    Any,
+}
+
+/// Matches a window by class and title, and optionally by the workspace it's on and its
+/// other properties.
+///
+/// Only the class and title matchers that are set take part, combined as follows. A set
+/// matcher never matches when there's no focused window, even if it's negated.
+///
+/// | class     | title     | `all` | `any` |
+/// |-----------|-----------|-------|-------|
+/// | unset     | unset     | yes   | yes   |
+/// | matches   | unset     | yes   | yes   |
+/// | no match  | unset     | no    | no    |
+/// | matches   | matches   | yes   | yes   |
+/// | matches   | no match  | no    | yes   |
+/// | no match  | matches   | no    | yes   |
+/// | no match  | no match  | no    | no    |
+///
+/// The workspace and window conditions must always hold on top of that.
+#[derive(Clone, PartialEq)]
+pub struct WindowRule {
+    window_class: Option<Matcher>,
+    window_title: Option<Matcher>,
+    combinator: Combinator,
+    workspace: Option<Matcher>,
+    conditions: WindowConditions,
+}
+
+pub fn new(window_class: Option<Matcher>, window_title: Option<Matcher>) -> WindowRule {
+    WindowRule {
+        window_class,
+        window_title,
+        combinator: Combinator::default(),
+        workspace: None,
+        conditions: WindowConditions::default(),
+    }
+}
+
+/// Rule matching any window, or no window at all.
+pub fn any() -> WindowRule {
+    new(None, None)
+}
+
+impl WindowRule {
+    pub fn with_combinator(mut self, combinator: Combinator) -> Self {
+        self.combinator = combinator;
+        self
+    }
+
+    /// Additionally requires the focused workspace's ID or name to match `workspace`.
+    pub fn with_workspace(mut self, workspace: Option<Matcher>) -> Self {
+        self.workspace = workspace;
+        self
+    }
+
+    /// Additionally requires the focused window to e.g. be fullscreen or have a given PID.
+    pub fn with_conditions(mut self, conditions: WindowConditions) -> Self {
+        self.conditions = conditions;
+        self
+    }
+
+    pub fn window_class(&self) -> Option<&Matcher> {
+        self.window_class.as_ref()
+    }
+
+    pub fn window_title(&self) -> Option<&Matcher> {
+        self.window_title.as_ref()
+    }
+
+    pub fn matches(&self, context: &WindowContext) -> bool {
+        self.matches_with(
+            context,
+            matcher_matches(&self.window_class, &context.class),
+            matcher_matches(&self.window_title, &context.title),
+        )
+    }
+
+    /// Like `matches`, with the class and title matchers already matched against the
+    /// context, e.g. by a `RuleSet`. The results of unset matchers are ignored.
+    pub(super) fn matches_with(
+        &self,
+        context: &WindowContext,
+        class_match: bool,
+        title_match: bool,
+    ) -> bool {
+        if !self.conditions.matches(context) {
+            return false;
+        }
+
+        if let Some(matcher) = &self.workspace {
+            if !context.workspace.as_ref().is_some_and(|workspace| {
+                matcher.is_match_any(&[&workspace.id.to_string(), &workspace.name])
+            }) {
+                return false;
+            }
+        }
+
+        let mut results = [
+            (&self.window_class, class_match),
+            (&self.window_title, title_match),
+        ]
+        .into_iter()
+        .filter(|(matcher, _)| matcher.is_some())
+        .map(|(_, result)| result)
+        .peekable();
+
+        if results.peek().is_none() {
+            return true;
+        }
+
+        match self.combinator {
+            Combinator::All => results.all(|result| result),
+            Combinator::Any => results.any(|result| result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::matcher;
+    use super::super::workspace::WorkspaceInfo;
+    use super::*;
+
+    fn window(class: &str, title: &str) -> WindowContext {
+        WindowContext {
+            class: Some(class.to_string()),
+            title: Some(title.to_string()),
+            initial_class: Some(class.to_string()),
+            initial_title: Some(title.to_string()),
+            pid: Some(1234),
+            ..WindowContext::default()
+        }
+    }
+
+    #[test]
+    fn test_truth_table() {
+        let firefox = || matcher::exact("firefox").ok();
+        let youtube = || matcher::glob("*YouTube*").ok();
+        let steam = || matcher::exact("steam").ok();
+        let docs = || matcher::regex("^Docs").ok();
+
+        // (class matcher, title matcher, expected with `all`, expected with `any`)
+        let rules = [
+            ("Unset, unset", None, None, true, true),
+            ("Matches, unset", firefox(), None, true, true),
+            ("No match, unset", steam(), None, false, false),
+            ("Unset, matches", None, youtube(), true, true),
+            ("Matches, matches", firefox(), youtube(), true, true),
+            ("Matches, no match", firefox(), docs(), false, true),
+            ("No match, matches", steam(), youtube(), false, true),
+            ("No match, no match", steam(), docs(), false, false),
+        ];
+        let context = window("firefox", "Cats - YouTube");
+        for (name, window_class, window_title, all, any) in rules {
+            let rule = new(window_class, window_title);
+            assert_eq!(rule.matches(&context), all, "{} (all)", name);
+
+            let rule = rule.with_combinator(Combinator::Any);
+            assert_eq!(rule.matches(&context), any, "{} (any)", name);
+        }
+    }
+
+    #[test]
+    fn test_missing_values() {
+        let not_firefox = || matcher::not(matcher::exact("firefox").unwrap());
+        let rules = [
+            ("No matchers", new(None, None), true),
+            ("Class", new(matcher::regex("").ok(), None), false),
+            ("Negated class", new(Some(not_firefox()), None), false),
+            (
+                "Negated class or title",
+                new(Some(not_firefox()), Some(not_firefox())).with_combinator(Combinator::Any),
+                false,
+            ),
+        ];
+        for (name, rule, expected) in rules {
+            assert_eq!(
+                rule.matches(&WindowContext::default()),
+                expected,
+                "{}",
+                name
+            );
+        }
+
+        // A title matcher doesn't match a window without a title
+        let rule = new(matcher::exact("firefox").ok(), matcher::regex("").ok());
+        let context = WindowContext {
+            title: None,
+            ..window("firefox", "")
+        };
+        assert!(!rule.matches(&context));
+    }
+
+    #[test]
+    fn test_negation() {
+        let rule = new(
+            Some(matcher::not(matcher::glob("steam_app_*").unwrap())),
+            None,
+        );
+
+        assert!(rule.matches(&window("firefox", "YouTube")));
+        assert!(!rule.matches(&window("steam_app_123", "Game")));
+    }
+
+    #[test]
+    fn test_workspace() {
+        let gaming = WorkspaceInfo {
+            id: 9,
+            name: "9".to_string(),
+        };
+        let named = WorkspaceInfo {
+            id: 3,
+            name: "games".to_string(),
+        };
+
+        let rules = [
+            ("Any window on workspace", "", "^9$", Some(&gaming), true),
+            ("Other workspace", "", "^9$", Some(&named), false),
+            ("Unknown workspace", "", "^9$", None, false),
+            ("Workspace name", "", "^(games)$", Some(&named), true),
+            (
+                "Class and workspace",
+                "^(discord)$",
+                "^9$",
+                Some(&gaming),
+                true,
+            ),
+            (
+                "Class doesn't match",
+                "^(steam)$",
+                "^9$",
+                Some(&gaming),
+                false,
+            ),
+        ];
+        for (name, window_class, workspace, active, expected) in rules {
+            let window_class = match window_class {
+                "" => None,
+                window_class => matcher::regex(window_class).ok(),
+            };
+            let rule = new(window_class, None).with_workspace(matcher::regex(workspace).ok());
+            let context = WindowContext {
+                workspace: active.cloned(),
+                ..window("discord", "Discord")
+            };
+            assert_eq!(rule.matches(&context), expected, "{}", name)
+        }
+
+        // A workspace-only rule also matches without a focused window
+        let rule = any().with_workspace(matcher::regex("^9$").ok());
+        assert!(rule.matches(&WindowContext {
+            workspace: Some(gaming.clone()),
+            ..WindowContext::default()
+        }));
+
+        // Negated workspaces don't match by ID or by name
+        let rule = any().with_workspace(Some(matcher::not(matcher::exact("games").unwrap())));
+        assert!(rule.matches(&WindowContext {
+            workspace: Some(gaming),
+            ..WindowContext::default()
+        }));
+        assert!(!rule.matches(&WindowContext {
+            workspace: Some(named),
+            ..WindowContext::default()
+        }));
+    }
+
+    #[test]
+    fn test_conditions() {
+        let fullscreen = WindowContext {
+            fullscreen: true,
+            ..window("firefox", "YouTube")
+        };
+        let floating_xwayland = WindowContext {
+            floating: true,
+            xwayland: true,
+            ..window("firefox", "YouTube")
+        };
+
+        let rules = [
+            (
+                "No conditions",
+                WindowConditions::default(),
+                &fullscreen,
+                true,
+            ),
+            (
+                "Fullscreen",
+                WindowConditions {
+                    fullscreen: Some(true),
+                    ..WindowConditions::default()
+                },
+                &fullscreen,
+                true,
+            ),
+            (
+                "Not fullscreen",
+                WindowConditions {
+                    fullscreen: Some(true),
+                    ..WindowConditions::default()
+                },
+                &floating_xwayland,
+                false,
+            ),
+            (
+                "No window",
+                WindowConditions {
+                    fullscreen: Some(true),
+                    ..WindowConditions::default()
+                },
+                &WindowContext::default(),
+                false,
+            ),
+            (
+                "Tiled native window",
+                WindowConditions {
+                    floating: Some(false),
+                    xwayland: Some(false),
+                    ..WindowConditions::default()
+                },
+                &fullscreen,
+                true,
+            ),
+            (
+                "Floating xwayland window",
+                WindowConditions {
+                    floating: Some(true),
+                    xwayland: Some(true),
+                    pinned: Some(false),
+                    ..WindowConditions::default()
+                },
+                &floating_xwayland,
+                true,
+            ),
+            (
+                "Initial class and title",
+                WindowConditions {
+                    initial_class: matcher::exact("firefox").ok(),
+                    initial_title: matcher::regex("YouTube").ok(),
+                    ..WindowConditions::default()
+                },
+                &fullscreen,
+                true,
+            ),
+            (
+                "Initial title doesn't match",
+                WindowConditions {
+                    initial_title: matcher::regex("^(Mozilla)").ok(),
+                    ..WindowConditions::default()
+                },
+                &fullscreen,
+                false,
+            ),
+            (
+                "PID",
+                WindowConditions {
+                    pid: Some(1234),
+                    ..WindowConditions::default()
+                },
+                &fullscreen,
+                true,
+            ),
+            (
+                "Other PID",
+                WindowConditions {
+                    pid: Some(1),
+                    ..WindowConditions::default()
+                },
+                &fullscreen,
+                false,
+            ),
+        ];
+        for (name, conditions, context, expected) in rules {
+            let rule = new(matcher::exact("firefox").ok(), None).with_conditions(conditions);
+            assert_eq!(rule.matches(context), expected, "{}", name)
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use hyprland::data::Workspace;
+use hyprland::shared::{HyprDataActive, WorkspaceId};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkspaceInfo {
+    pub id: WorkspaceId,
+    pub name: String,
+}
+
+/// Returns the focused workspace.
+pub fn get_active() -> hyprland::Result<WorkspaceInfo> {
+    let workspace = Workspace::get_active()?;
+
+    Ok(WorkspaceInfo {
+        id: workspace.id,
+        name: workspace.name,
+    })
+}
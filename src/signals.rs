@@ -0,0 +1,60 @@
+use std::thread;
+
+use log::info;
+use signal_hook::consts::{SIGINT, SIGTERM, SIGUSR1, SIGUSR2};
+use signal_hook::iterator::Signals;
+
+#[derive(Debug, PartialEq)]
+pub enum Signal {
+    // SIGUSR1
+    ToggleNightLight,
+    // SIGUSR2, sent by `systemctl reload`
+    Reload,
+    // SIGTERM or SIGINT
+    Terminate,
+}
+
+fn from_raw(signal: i32) -> Option<Signal> {
+    match signal {
+        SIGUSR1 => Some(Signal::ToggleNightLight),
+        SIGUSR2 => Some(Signal::Reload),
+        SIGTERM | SIGINT => Some(Signal::Terminate),
+        _ => None,
+    }
+}
+
+/// Spawns a thread that calls `handler` for every received signal.
+pub fn spawn<F>(handler: F) -> std::io::Result<thread::JoinHandle<()>>
+where
+    F: Fn(Signal) + Send + 'static,
+{
+    let mut signals = Signals::new([SIGUSR1, SIGUSR2, SIGTERM, SIGINT])?;
+
+    Ok(thread::spawn(move || {
+        for signal in signals.forever() {
+            if let Some(signal) = from_raw(signal) {
+                info!("Received signal: {:?}", signal);
+                handler(signal);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_raw() {
+        let signals = [
+            (SIGUSR1, Some(Signal::ToggleNightLight)),
+            (SIGUSR2, Some(Signal::Reload)),
+            (SIGTERM, Some(Signal::Terminate)),
+            (SIGINT, Some(Signal::Terminate)),
+            (signal_hook::consts::SIGHUP, None),
+        ];
+        for (signal, expected) in signals {
+            assert_eq!(from_raw(signal), expected)
+        }
+    }
+}
@@ -1,21 +1,75 @@
-use chrono::{Local, NaiveTime};
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+
+const MOCK_DATE: &str = "2024-01-01";
+const MOCK_FMT: &str = "%Y-%m-%d %H:%M";
 
 #[derive(Clone, PartialEq)]
 pub struct Time {
-    mock_time: Option<NaiveTime>,
+    mock_time: Option<DateTime<FixedOffset>>,
 }
 
 impl Time {
-    pub fn new(mock_time: Option<NaiveTime>) -> Self {
+    pub fn new(mock_time: Option<DateTime<FixedOffset>>) -> Self {
         Self { mock_time }
     }
 
+    /// Mocks the given `%Y-%m-%d %H:%M` or `%H:%M` time in UTC.
+    pub fn mock(value: &str) -> Self {
+        let value = match value.contains('-') {
+            true => value.to_string(),
+            false => format!("{} {}", MOCK_DATE, value),
+        };
+
+        Self::new(Some(
+            NaiveDateTime::parse_from_str(&value, MOCK_FMT)
+                .unwrap()
+                .and_utc()
+                .fixed_offset(),
+        ))
+    }
+
     pub fn now(&self) -> NaiveTime {
         match self.mock_time {
-            Some(p) => p,
+            Some(p) => p.time(),
             None => Local::now().time(),
         }
     }
+
+    pub fn today(&self) -> NaiveDate {
+        match self.mock_time {
+            Some(p) => p.date_naive(),
+            None => Local::now().date_naive(),
+        }
+    }
+
+    /// Converts a UTC timestamp to the (mocked) local time of day.
+    pub fn to_local(&self, time: DateTime<Utc>) -> NaiveTime {
+        match self.mock_time {
+            Some(p) => time.with_timezone(p.offset()).time(),
+            None => time.with_timezone(&Local).time(),
+        }
+    }
+}
+
+/// Time from `from` until the next occurrence of `to`.
+pub fn until(from: NaiveTime, to: NaiveTime) -> TimeDelta {
+    let until = to - from;
+    if until < TimeDelta::zero() {
+        return until + TimeDelta::days(1);
+    }
+
+    until
+}
+
+/// Whether `now` is within the inclusive window, which may span midnight.
+pub fn is_between(now: NaiveTime, start_time: NaiveTime, end_time: NaiveTime) -> bool {
+    if start_time < end_time {
+        return start_time <= now && now <= end_time;
+    }
+
+    now >= start_time || now <= end_time
 }
 
 pub fn int_in_range(value: i32, min: i32, max: i32) -> i32 {
@@ -29,18 +83,46 @@ pub fn int_in_range(value: i32, min: i32, max: i32) -> i32 {
     value
 }
 
-pub fn shader_hash_from_path(path: String) -> Option<String> {
-    Some(path.split("/").last().unwrap().to_string())
+/// Parses durations like `90s`, `30m` or `1h30m`.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total: u64 = 0;
+    let mut number = String::new();
+
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let multiplier = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        total = number
+            .parse::<u64>()
+            .ok()?
+            .checked_mul(multiplier)
+            .and_then(|seconds| total.checked_add(seconds))?;
+        number.clear();
+    }
+
+    if !number.is_empty() || total == 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs(total))
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
 
     #[test]
     fn test_time_mock() {
-        let time = Time::new(Some(NaiveTime::parse_from_str("12:00", "%H:%M").unwrap()));
+        let time = Time::mock("12:00");
         assert_eq!(
             time.now(),
             NaiveTime::parse_from_str("12:00", "%H:%M").unwrap()
@@ -48,6 +130,56 @@ mod tests {
         assert!(time.now() > NaiveTime::parse_from_str("11:00", "%H:%M").unwrap());
         assert!(time.now() > NaiveTime::parse_from_str("11:59", "%H:%M").unwrap());
         assert!(time.now() < NaiveTime::parse_from_str("12:59", "%H:%M").unwrap());
+        assert_eq!(
+            time.today(),
+            NaiveDate::parse_from_str(MOCK_DATE, "%Y-%m-%d").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_date_mock() {
+        let time = Time::mock("2024-06-21 23:30");
+        assert_eq!(
+            time.now(),
+            NaiveTime::parse_from_str("23:30", "%H:%M").unwrap()
+        );
+        assert_eq!(
+            time.today(),
+            NaiveDate::parse_from_str("2024-06-21", "%Y-%m-%d").unwrap()
+        );
+        assert_eq!(
+            time.to_local(
+                NaiveDateTime::parse_from_str("2024-06-21 19:00", MOCK_FMT)
+                    .unwrap()
+                    .and_utc()
+            ),
+            NaiveTime::parse_from_str("19:00", "%H:%M").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let tests = [
+            ("90s", Some(90)),
+            ("30m", Some(30 * 60)),
+            ("1h30m", Some(90 * 60)),
+            ("1d", Some(24 * 60 * 60)),
+            ("30", None),
+            ("0m", None),
+            ("m", None),
+            ("30x", None),
+            ("", None),
+            ("9999999999999999d", None),
+            ("18446744073709551615s1s", None),
+        ];
+        for (value, expected) in tests {
+            assert_eq!(
+                parse_duration(value),
+                expected.map(Duration::from_secs),
+                "{}",
+                value
+            )
+        }
     }
 
     #[test]
@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, error};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
+/// Watches the file at `path` and calls `on_change` once writes to it have settled.
+///
+/// The parent directory is watched instead of the file itself, so editors that save by
+/// writing a new file and renaming it over the old one keep triggering reloads.
+/// The returned watcher stops watching when dropped.
+pub fn spawn<F>(path: &Path, on_change: F) -> notify::Result<RecommendedWatcher>
+where
+    F: Fn() + Send + 'static,
+{
+    let path = absolute(path);
+    let file_name = path.file_name().map(|name| name.to_os_string());
+    let parent = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(
+        move |event: notify::Result<Event>| match event {
+            Ok(event) => {
+                if event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name().map(|name| name.to_os_string()) == file_name)
+                {
+                    let _ = tx.send(());
+                }
+            }
+            Err(error) => error!("Watch error: {:?}", error),
+        },
+        notify::Config::default()
+            .with_poll_interval(Duration::from_secs(2))
+            .with_compare_contents(false),
+    )?;
+    watcher.watch(&parent, RecursiveMode::NonRecursive)?;
+
+    thread::spawn(move || {
+        while rx.recv().is_ok() {
+            // Wait for the burst of events of a single save to end
+            loop {
+                match rx.recv_timeout(DEBOUNCE_DELAY) {
+                    Ok(()) => debug!("Ignoring duplicate event within debounce period"),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            on_change();
+        }
+    });
+
+    Ok(watcher)
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => std::env::current_dir()
+            .map(|dir| dir.join(path))
+            .unwrap_or(path.to_path_buf()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_atomic_saves() {
+        let dir = std::env::temp_dir().join(format!("hyprlux-watcher-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hyprlux.toml");
+        fs::write(&path, "hot_reload = true").unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let calls_clone = Arc::clone(&calls);
+        let _watcher = spawn(&path, move || {
+            calls_clone.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+        // Save like vim does: write a new file and rename it over the old one
+        for i in 0..2 {
+            let tmp = dir.join("hyprlux.toml.tmp");
+            fs::write(&tmp, format!("hot_reload = true # {}", i)).unwrap();
+            fs::rename(&tmp, &path).unwrap();
+            thread::sleep(DEBOUNCE_DELAY * 3);
+        }
+
+        // Unrelated files in the directory are ignored
+        fs::write(dir.join("other.toml"), "").unwrap();
+        thread::sleep(DEBOUNCE_DELAY * 3);
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
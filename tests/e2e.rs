@@ -0,0 +1,146 @@
+mod fake_hyprland;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use chrono::{Local, TimeDelta};
+
+const EMPTY: &str = "[[EMPTY]]";
+
+/// Night light config that is on for the next hour when `active`, off otherwise.
+fn night_light(active: bool) -> String {
+    let now = Local::now();
+    let (start, end) = match active {
+        true => (now - TimeDelta::hours(1), now + TimeDelta::hours(1)),
+        false => (now + TimeDelta::hours(1), now + TimeDelta::hours(2)),
+    };
+
+    format!(
+        "[night_light]\nenabled = true\nstart_time = \"{}\"\nend_time = \"{}\"\ntemperature = 3500\n",
+        start.format("%H:%M"),
+        end.format("%H:%M")
+    )
+}
+
+fn vibrance(strength: i32) -> String {
+    format!(
+        "[[vibrance_configs]]\nwindow_class = \"steam_app_1\"\nstrength = {}\n",
+        strength
+    )
+}
+
+#[test]
+fn test_night_light() {
+    let hyprland = fake_hyprland::new("night_light");
+    let mut hyprlux = hyprland.spawn_hyprlux(&night_light(true));
+
+    hyprland.focus("firefox", "Mozilla Firefox");
+    assert_eq!(hyprland.wait_for_screen_shaders(1), ["night_3500"]);
+    let dir = fs::metadata(hyprland.shader_dir()).unwrap();
+    assert_eq!(dir.permissions().mode() & 0o777, 0o700);
+
+    // Shutting down restores the screen and removes the shader files
+    hyprlux.terminate();
+    assert_eq!(hyprland.screen_shaders(), ["night_3500", EMPTY]);
+    assert_eq!(fs::read_dir(hyprland.shader_dir()).unwrap().count(), 0);
+}
+
+#[test]
+fn test_night_light_inactive() {
+    let hyprland = fake_hyprland::new("night_light_inactive");
+    let _hyprlux = hyprland.spawn_hyprlux(&(night_light(false) + &vibrance(100)));
+
+    hyprland.focus("firefox", "Mozilla Firefox");
+    hyprland.focus("steam_app_1", "Game");
+    assert_eq!(hyprland.wait_for_screen_shaders(1), ["vibrance_100"]);
+}
+
+#[test]
+fn test_vibrance() {
+    let hyprland = fake_hyprland::new("vibrance");
+    let _hyprlux = hyprland.spawn_hyprlux(&(night_light(true) + &vibrance(100)));
+
+    let focus = [
+        ("steam_app_1", vec!["night_3500+vibrance_100"]),
+        // Unchanged shaders are not re-applied
+        ("steam_app_1", vec![]),
+        ("firefox", vec!["night_3500"]),
+    ];
+    let mut expected = Vec::new();
+    for (class, shaders) in focus {
+        hyprland.focus(class, "Window");
+        expected.extend(shaders);
+    }
+
+    assert_eq!(hyprland.wait_for_screen_shaders(expected.len()), expected);
+}
+
+#[test]
+fn test_remove() {
+    let hyprland = fake_hyprland::new("remove");
+    let _hyprlux = hyprland.spawn_hyprlux(&vibrance(100));
+
+    hyprland.focus("steam_app_1", "Game");
+    hyprland.focus("firefox", "Mozilla Firefox");
+    // Nothing is removed when there is no shader
+    hyprland.focus("kitty", "Terminal");
+    hyprland.focus("steam_app_1", "Game");
+
+    assert_eq!(
+        hyprland.wait_for_screen_shaders(3),
+        ["vibrance_100", EMPTY, "vibrance_100"]
+    );
+}
+
+#[test]
+fn test_hot_reload() {
+    let hyprland = fake_hyprland::new("hot_reload");
+    let _hyprlux = hyprland.spawn_hyprlux(&("hot_reload = true\n".to_string() + &vibrance(100)));
+
+    hyprland.focus("steam_app_1", "Game");
+    assert_eq!(hyprland.wait_for_screen_shaders(1), ["vibrance_100"]);
+
+    // The focused window gets the reloaded config's shader
+    hyprland.write_config(&("hot_reload = true\n".to_string() + &vibrance(200)));
+    assert_eq!(
+        hyprland.wait_for_screen_shaders(2),
+        ["vibrance_100", "vibrance_200"]
+    );
+}
+
+#[test]
+fn test_respect_external_shader() {
+    let hyprland = fake_hyprland::new("respect_external_shader");
+    let mut hyprlux =
+        hyprland.spawn_hyprlux(&("respect_external_shader = true\n".to_string() + &vibrance(100)));
+    hyprland.set_screen_shader("/home/user/.config/hypr/shader.frag");
+
+    hyprland.focus("steam_app_1", "Game");
+    hyprland.focus("firefox", "Mozilla Firefox");
+    hyprlux.terminate();
+
+    assert_eq!(hyprland.screen_shaders(), ["shader.frag"]);
+}
+
+#[test]
+fn test_baseline() {
+    let hyprland = fake_hyprland::new("baseline");
+    hyprland.set_screen_shader("/home/user/.config/hypr/base.frag");
+    let mut hyprlux = hyprland.spawn_hyprlux(&vibrance(100));
+
+    hyprland.focus("steam_app_1", "Game");
+    hyprland.focus("firefox", "Mozilla Firefox");
+    hyprland.focus("steam_app_1", "Game");
+    hyprlux.terminate();
+
+    assert_eq!(
+        hyprland.screen_shaders(),
+        [
+            "base.frag",
+            "vibrance_100",
+            "base.frag",
+            "vibrance_100",
+            "base.frag"
+        ]
+    );
+}
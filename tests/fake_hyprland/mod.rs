@@ -0,0 +1,269 @@
+//! A fake Hyprland instance that answers the IPC requests hyprlux makes and records the
+//! screen shaders it sets, so the real binary can be tested without a compositor.
+
+use std::fs::{self, Permissions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{env, thread};
+
+const SIGNATURE: &str = "fake";
+const SHADER_KEY: &str = "decoration:screen_shader";
+const NO_SHADER: &str = "[[EMPTY]]";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct FakeHyprland {
+    dir: PathBuf,
+    state: Arc<Mutex<State>>,
+    listeners: Arc<Mutex<Vec<UnixStream>>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Every `decoration:screen_shader` value set, in order, with its name.
+    screen_shaders: Vec<(String, String)>,
+    /// Class and title of the focused window.
+    active_window: Option<(String, String)>,
+    active_window_requests: usize,
+}
+
+/// The hyprlux daemon, killed when dropped.
+pub struct Hyprlux {
+    child: Child,
+}
+
+/// Starts a fake instance in a fresh directory named after the test.
+pub fn new(name: &str) -> FakeHyprland {
+    let dir = env::temp_dir().join(format!("hyprlux-e2e-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    let sockets = dir.join("hypr").join(SIGNATURE);
+    fs::create_dir_all(&sockets).unwrap();
+    // Also the daemon's runtime dir, which must be private
+    fs::set_permissions(&dir, Permissions::from_mode(0o700)).unwrap();
+
+    let state = Arc::new(Mutex::new(State::default()));
+    let requests = UnixListener::bind(sockets.join(".socket.sock")).unwrap();
+    let state_clone = Arc::clone(&state);
+    thread::spawn(move || {
+        for stream in requests.incoming().flatten() {
+            let _ = answer(stream, &state_clone);
+        }
+    });
+
+    let listeners = Arc::new(Mutex::new(Vec::new()));
+    let events = UnixListener::bind(sockets.join(".socket2.sock")).unwrap();
+    let listeners_clone = Arc::clone(&listeners);
+    thread::spawn(move || {
+        for stream in events.incoming().flatten() {
+            listeners_clone.lock().unwrap().push(stream);
+        }
+    });
+
+    FakeHyprland {
+        dir,
+        state,
+        listeners,
+    }
+}
+
+/// Hash of a shader written by hyprlux, looked up in its manifest while the file still exists,
+/// or the file name of any other shader.
+fn name(value: &str) -> String {
+    let path = Path::new(value);
+    let file_name = path
+        .file_name()
+        .map_or(value, |name| name.to_str().unwrap());
+    let manifest = path
+        .parent()
+        .and_then(|dir| fs::read_to_string(dir.join("manifest")).ok())
+        .unwrap_or_default();
+
+    manifest
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("{} ", file_name)))
+        .unwrap_or(file_name)
+        .to_string()
+}
+
+/// Answers a single request on the command socket.
+fn answer(mut stream: UnixStream, state: &Mutex<State>) -> io::Result<()> {
+    let mut buffer = [0; 4096];
+    let read = stream.read(&mut buffer)?;
+    let request = String::from_utf8_lossy(&buffer[..read]).to_string();
+    let (command, args) = request.split_once(' ').unwrap_or((&request, ""));
+
+    let mut state = state.lock().unwrap();
+    // Values are formatted with `{:?}`, which is valid JSON for the plain names used here
+    let response = match (command, args) {
+        ("/keyword", args) => {
+            if let Some((SHADER_KEY, value)) = args.split_once(' ') {
+                state.screen_shaders.push((value.to_string(), name(value)));
+            }
+            "ok".to_string()
+        }
+        ("j/getoption", SHADER_KEY) => format!(
+            r#"{{"option": {:?}, "str": {:?}, "set": true}}"#,
+            SHADER_KEY,
+            state
+                .screen_shaders
+                .last()
+                .map_or(NO_SHADER, |(value, _)| value.as_str())
+        ),
+        ("j/activewindow", _) => {
+            state.active_window_requests += 1;
+            match &state.active_window {
+                Some((class, title)) => format!(
+                    r#"{{"address": "0x1", "at": [0, 0], "size": [800, 600],
+                "workspace": {{"id": 1, "name": "1"}}, "floating": false, "fullscreen": 0,
+                "fullscreenClient": 0, "monitor": 0, "initialClass": {0:?}, "class": {0:?},
+                "initialTitle": {1:?}, "title": {1:?}, "pid": 1, "xwayland": false,
+                "pinned": false, "grouped": [], "mapped": true, "swallowing": null,
+                "focusHistoryID": 0}}"#,
+                    class, title
+                ),
+                None => "{}".to_string(),
+            }
+        }
+        ("j/activeworkspace", _) => r#"{"id": 1, "name": "1", "monitor": "DP-1",
+            "monitorID": 0, "windows": 1, "hasfullscreen": false, "lastwindow": "0x1",
+            "lastwindowtitle": ""}"#
+            .to_string(),
+        ("j/monitors", _) => "[]".to_string(),
+        _ => "unknown request".to_string(),
+    };
+
+    stream.write_all(response.as_bytes())
+}
+
+impl FakeHyprland {
+    pub fn config_path(&self) -> PathBuf {
+        self.dir.join("hyprlux.toml")
+    }
+
+    pub fn write_config(&self, config: &str) {
+        fs::write(self.config_path(), config).unwrap();
+    }
+
+    /// Writes the config and runs hyprlux against this instance, returning once it listens
+    /// for events.
+    pub fn spawn_hyprlux(&self, config: &str) -> Hyprlux {
+        self.write_config(config);
+
+        let child = Command::new(env!("CARGO_BIN_EXE_hyprlux"))
+            .arg(self.config_path())
+            .env("HYPRLAND_INSTANCE_SIGNATURE", SIGNATURE)
+            .env("XDG_RUNTIME_DIR", &self.dir)
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("XDG_STATE_HOME", self.dir.join("state"))
+            .env("TMPDIR", &self.dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let hyprlux = Hyprlux { child };
+
+        self.wait_for("the event listener", || {
+            !self.listeners.lock().unwrap().is_empty()
+        });
+        hyprlux
+    }
+
+    /// Focuses a window and emits the events Hyprland sends for it. Returns once hyprlux
+    /// looked up the window, so that the next focus change can't overtake it.
+    pub fn focus(&self, class: &str, title: &str) {
+        let requests = {
+            let mut state = self.state.lock().unwrap();
+            state.active_window = Some((class.to_string(), title.to_string()));
+            state.active_window_requests
+        };
+        self.emit(&format!(
+            "activewindow>>{},{}\nactivewindowv2>>1\n",
+            class, title
+        ));
+
+        self.wait_for(&format!("hyprlux to look up {}", class), || {
+            self.state.lock().unwrap().active_window_requests > requests
+        });
+    }
+
+    /// Sets the screen shader as another tool or hyprland.conf would.
+    pub fn set_screen_shader(&self, path: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .screen_shaders
+            .push((path.to_string(), name(path)));
+    }
+
+    fn emit(&self, events: &str) {
+        for listener in self.listeners.lock().unwrap().iter_mut() {
+            listener.write_all(events.as_bytes()).unwrap();
+        }
+    }
+
+    /// The screen shaders set so far, by hash, file name or `[[EMPTY]]`.
+    pub fn screen_shaders(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .screen_shaders
+            .iter()
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+
+    /// Directory hyprlux writes its shaders to.
+    pub fn shader_dir(&self) -> PathBuf {
+        self.dir.join("hyprlux")
+    }
+
+    /// Waits until `count` screen shaders were set and returns them.
+    pub fn wait_for_screen_shaders(&self, count: usize) -> Vec<String> {
+        self.wait_for(&format!("{} screen shaders", count), || {
+            self.state.lock().unwrap().screen_shaders.len() >= count
+        });
+        self.screen_shaders()
+    }
+
+    fn wait_for(&self, what: &str, done: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !done() {
+            assert!(
+                start.elapsed() < TIMEOUT,
+                "Timed out waiting for {}, screen shaders: {:?}",
+                what,
+                self.screen_shaders()
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl Hyprlux {
+    /// Stops the daemon the way a service manager would.
+    pub fn terminate(&mut self) {
+        Command::new("kill")
+            .arg("-TERM")
+            .arg(self.child.id().to_string())
+            .status()
+            .unwrap();
+        self.child.wait().unwrap();
+    }
+}
+
+impl Drop for FakeHyprland {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl Drop for Hyprlux {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,391 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Local;
+use hyprlux::compositor::fake::{self, FakeCompositor};
+use hyprlux::compositor::{Compositor, Event};
+use hyprlux::daemon::{self, ConfigData};
+use hyprlux::shaders::monitor::{self, MonitorInfo};
+use hyprlux::shaders::rule::{self, Effect, Rule};
+use hyprlux::shaders::store::{self, ShaderStore};
+use hyprlux::shaders::window_context::WindowContext;
+use hyprlux::shaders::window_rule;
+use hyprlux::shaders::{matcher, night_light, vibrance};
+
+/// Directory of the shader stores of this test run.
+fn stores() -> PathBuf {
+    std::env::temp_dir().join(format!("hyprlux-pipeline-{}", std::process::id()))
+}
+
+/// A store of its own, as tests run in parallel and each cleans up unused shaders.
+fn store() -> ShaderStore {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    store::new(stores().join(NEXT.fetch_add(1, Ordering::Relaxed).to_string()))
+}
+
+fn config(rules: Vec<Rule>) -> ConfigData {
+    ConfigData {
+        rules: rule::list(rules).unwrap(),
+        store: store(),
+        ..ConfigData::default()
+    }
+}
+
+fn night(mock_time: &str) -> Rule {
+    rule::new(
+        window_rule::any(),
+        vec![Effect::Temperature(
+            night_light::new(
+                true,
+                "20:00".to_string(),
+                "06:00".to_string(),
+                3500,
+                Some(mock_time.to_string()),
+            )
+            .unwrap(),
+        )],
+    )
+}
+
+fn steam_vibrance() -> Rule {
+    rule::new(
+        window_rule::new(matcher::exact("steam").ok(), None),
+        vec![Effect::Vibrance(vibrance::new(100))],
+    )
+}
+
+fn window(class: &str) -> WindowContext {
+    WindowContext {
+        class: Some(class.to_string()),
+        ..WindowContext::default()
+    }
+}
+
+/// Hash of the current screen shader, or the file name of one hyprlux didn't write. `None`
+/// if there is none.
+fn applied(compositor: &FakeCompositor) -> Option<String> {
+    let path = compositor.screen_shader().unwrap()?;
+    let file = Path::new(&path);
+
+    match file.starts_with(stores()) {
+        true => store::new(file.parent().unwrap().to_path_buf()).hash(&path),
+        false => Some(file.file_name().unwrap().to_str().unwrap().to_string()),
+    }
+}
+
+/// Delivers the queued events to the daemon, as the compositor's event stream would.
+fn listen(compositor: &Arc<FakeCompositor>, config_data: &Arc<Mutex<ConfigData>>) {
+    let compositor_clone = Arc::clone(compositor);
+    let config_data = Arc::clone(config_data);
+    compositor
+        .listen(Box::new(move |event| {
+            daemon::handle_event(
+                compositor_clone.as_ref(),
+                &mut config_data.lock().unwrap(),
+                event,
+            )
+        }))
+        .unwrap();
+}
+
+#[test]
+fn test_focus_change() {
+    let compositor = Arc::new(fake::new());
+    let config_data = Arc::new(Mutex::new(config(vec![steam_vibrance()])));
+
+    let focus = [
+        ("firefox", None),
+        ("steam", Some("vibrance_100")),
+        ("steam", Some("vibrance_100")),
+        ("firefox", None),
+    ];
+    for (class, expected) in focus {
+        compositor.set_active_window(window(class));
+        compositor.push_event(Event::ActiveWindowChanged);
+        listen(&compositor, &config_data);
+
+        assert_eq!(applied(&compositor).as_deref(), expected, "{}", class);
+    }
+
+    // Unchanged shaders are not re-applied, and nothing is removed before anything is set
+    assert_eq!(
+        compositor
+            .history()
+            .iter()
+            .map(|path| path.is_some())
+            .collect::<Vec<bool>>(),
+        vec![true, false]
+    );
+
+    // The removed shader's file is cleaned up
+    let path = compositor.history()[0].clone().unwrap();
+    assert!(!Path::new(&path).exists());
+}
+
+#[test]
+fn test_time_change() {
+    let compositor = fake::new();
+    compositor.set_active_window(window("steam"));
+
+    let times = [
+        (
+            "19:00",
+            Some(Duration::from_secs(60 * 60 + 1)),
+            "vibrance_100",
+        ),
+        (
+            "21:00",
+            Some(Duration::from_secs(9 * 60 * 60 + 1)),
+            "night_3500+vibrance_100",
+        ),
+        (
+            "07:00",
+            Some(Duration::from_secs(13 * 60 * 60 + 1)),
+            "vibrance_100",
+        ),
+    ];
+    let mut config_data = config(Vec::new());
+    for (time, next_change, expected) in times {
+        // Boundaries are inclusive, so shaders change a second after them. The scheduler
+        // re-evaluates the rules once the next change is due
+        daemon::replace_config(
+            &mut config_data,
+            config(vec![night(time), steam_vibrance()]),
+        );
+        daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+
+        assert_eq!(config_data.next_change(), next_change, "{}", time);
+        assert_eq!(applied(&compositor).as_deref(), Some(expected), "{}", time);
+    }
+}
+
+#[test]
+fn test_monitor_added() {
+    let compositor = Arc::new(fake::new());
+    compositor.set_active_window(window("steam"));
+    let config_data = Arc::new(Mutex::new(config(vec![
+        steam_vibrance().with_monitor(Some(monitor::new("DP-1".to_string()).unwrap()))
+    ])));
+
+    daemon::apply_shaders_for_active_window(compositor.as_ref(), &mut config_data.lock().unwrap());
+    assert_eq!(applied(&compositor), None);
+
+    compositor.set_monitors(vec![
+        MonitorInfo {
+            id: 0,
+            name: "HDMI-A-1".to_string(),
+            description: "Dell IPS".to_string(),
+        },
+        MonitorInfo {
+            id: 1,
+            name: "DP-1".to_string(),
+            description: "Samsung OLED G8".to_string(),
+        },
+    ]);
+    compositor.push_event(Event::MonitorAdded("DP-1".to_string()));
+    listen(&compositor, &config_data);
+
+    assert_eq!(applied(&compositor).as_deref(), Some("vibrance_100@1"));
+    assert!(compositor
+        .shader_source()
+        .unwrap()
+        .contains("if (wl_output == 1) color = pass0(color);"));
+}
+
+#[test]
+fn test_night_light_with_rule_temperature() {
+    let config_data = config(vec![
+        night("22:00").with_name(daemon::NIGHT_LIGHT_RULE),
+        rule::new(
+            window_rule::any(),
+            vec![Effect::Temperature(night_light::new_always(5000, None))],
+        )
+        .with_priority(10),
+    ]);
+
+    let night_light = config_data.night_light().unwrap();
+    assert_eq!(night_light.temperature(), 3500);
+    assert_eq!(night_light.until_end(), Duration::from_secs(8 * 60 * 60));
+    assert!(config_data
+        .status()
+        .contains("night light: enabled, active (3500K)"));
+}
+
+#[test]
+fn test_pause() {
+    let compositor = fake::new();
+    compositor.set_active_window(window("steam"));
+    let mut config_data = config(vec![steam_vibrance()]);
+
+    daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+    assert_eq!(applied(&compositor).as_deref(), Some("vibrance_100"));
+
+    config_data.paused_until = Some(Local::now() + Duration::from_secs(60));
+    daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+    assert_eq!(applied(&compositor), None);
+
+    config_data.paused_until = None;
+    daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+    assert_eq!(applied(&compositor).as_deref(), Some("vibrance_100"));
+}
+
+#[test]
+fn test_external_shader() {
+    for respect in [false, true] {
+        let compositor = fake::new();
+        compositor.set_active_window(window("steam"));
+        let mut config_data = ConfigData {
+            respect_external_shader: respect,
+            ..config(vec![steam_vibrance()])
+        };
+
+        // Another tool sets a shader, which is found once hyprlux wants to change it
+        compositor
+            .set_screen_shader(Some("/home/user/shader.frag"))
+            .unwrap();
+        daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+        assert_eq!(
+            applied(&compositor).as_deref(),
+            Some(if respect {
+                "shader.frag"
+            } else {
+                "vibrance_100"
+            }),
+            "{}",
+            respect
+        );
+
+        // Hyprlux takes over again once the other shader is removed
+        compositor.set_screen_shader(None).unwrap();
+        compositor.set_active_window(window("firefox"));
+        daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+        compositor.set_active_window(window("steam"));
+        daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+        assert_eq!(
+            applied(&compositor).as_deref(),
+            Some("vibrance_100"),
+            "{}",
+            respect
+        );
+    }
+}
+
+#[test]
+fn test_config_reloaded() {
+    let compositor = Arc::new(fake::new());
+    compositor.set_active_window(window("steam"));
+    let config_data = Arc::new(Mutex::new(config(vec![steam_vibrance()])));
+
+    compositor.push_event(Event::ActiveWindowChanged);
+    listen(&compositor, &config_data);
+    assert_eq!(applied(&compositor).as_deref(), Some("vibrance_100"));
+
+    // Focus changes trust the applied shader, a reload of hyprland.conf doesn't
+    compositor.set_screen_shader(None).unwrap();
+    compositor.push_event(Event::ActiveWindowChanged);
+    listen(&compositor, &config_data);
+    assert_eq!(applied(&compositor), None);
+
+    compositor.push_event(Event::ConfigReloaded);
+    listen(&compositor, &config_data);
+    assert_eq!(applied(&compositor).as_deref(), Some("vibrance_100"));
+}
+
+#[test]
+fn test_baseline() {
+    let baseline = std::env::temp_dir().join(format!(
+        "hyprlux-pipeline-baseline-{}.frag",
+        std::process::id()
+    ));
+    fs::write(
+        &baseline,
+        "precision mediump float;\nvarying vec2 v_texcoord;\nuniform sampler2D tex;\n\nvoid main() {\n    gl_FragColor = texture2D(tex, v_texcoord);\n}\n",
+    )
+    .unwrap();
+    let baseline = baseline.to_str().unwrap().to_string();
+
+    for compose in [false, true] {
+        let compositor = fake::new();
+        compositor.set_screen_shader(Some(&baseline)).unwrap();
+        let mut config_data = ConfigData {
+            compose_baseline: compose,
+            ..config(vec![steam_vibrance()])
+        };
+        daemon::capture_baseline(&compositor, &mut config_data);
+
+        compositor.set_active_window(window("steam"));
+        daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+        match compose {
+            true => {
+                let hash = applied(&compositor).unwrap();
+                assert!(hash.starts_with("baseline_"), "{}", hash);
+                assert!(hash.ends_with("+vibrance_100"), "{}", hash);
+                assert!(compositor
+                    .shader_source()
+                    .unwrap()
+                    .contains("void hyprlux_baseline()"));
+
+                // Editing the baseline re-applies it
+                let source = fs::read_to_string(&baseline).unwrap();
+                fs::write(&baseline, format!("{}// edited\n", source)).unwrap();
+                daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+                assert_ne!(applied(&compositor).unwrap(), hash);
+                assert!(compositor.shader_source().unwrap().contains("// edited"));
+            }
+            false => assert_eq!(applied(&compositor).as_deref(), Some("vibrance_100")),
+        }
+
+        // The baseline comes back when no effects apply or when paused
+        compositor.set_active_window(window("firefox"));
+        daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+        assert_eq!(compositor.screen_shader().unwrap(), Some(baseline.clone()));
+
+        compositor.set_active_window(window("steam"));
+        daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+        config_data.paused_until = Some(Local::now() + Duration::from_secs(60));
+        daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+        assert_eq!(compositor.screen_shader().unwrap(), Some(baseline.clone()));
+    }
+
+    fs::remove_file(baseline).unwrap();
+}
+
+#[test]
+fn test_previous_run() {
+    let compositor = fake::new();
+    let mut config_data = config(vec![steam_vibrance()]);
+    compositor
+        .set_screen_shader(Some("/home/user/.config/hypr/shader.frag"))
+        .unwrap();
+    daemon::capture_baseline(&compositor, &mut config_data);
+
+    // A run that doesn't exit cleanly leaves its shader behind
+    let path = config_data
+        .store
+        .write("vibrance_100", "void main() {}")
+        .unwrap();
+    compositor.set_screen_shader(Some(&path)).unwrap();
+
+    // The next run recognises the shader and restores the baseline recorded before
+    let mut config_data = ConfigData {
+        store: config_data.store.clone(),
+        ..config(vec![steam_vibrance()])
+    };
+    daemon::capture_baseline(&compositor, &mut config_data);
+    assert_eq!(
+        config_data.baseline.as_deref(),
+        Some("/home/user/.config/hypr/shader.frag")
+    );
+
+    compositor.set_active_window(window("firefox"));
+    daemon::apply_shaders_for_active_window(&compositor, &mut config_data);
+    assert_eq!(
+        compositor.screen_shader().unwrap().as_deref(),
+        Some("/home/user/.config/hypr/shader.frag")
+    );
+    assert!(!Path::new(&path).exists());
+}